@@ -0,0 +1,71 @@
+//! Desktop notifications.
+//!
+//! Notifications are sent through the [`Notifier`] trait, so callers can
+//! swap the platform implementation ([`DesktopNotifier`]) for a mock.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+
+use std::fmt;
+
+#[cfg(target_os = "linux")]
+pub use linux::DesktopNotifier;
+#[cfg(target_os = "macos")]
+pub use macos::DesktopNotifier;
+
+/// Application name shown with every notification.
+pub const APP_NAME: &str = "xshuttle";
+
+/// How urgent a notification is.
+///
+/// The values match the freedesktop notification spec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Urgency {
+    Low = 0,
+    #[default]
+    Normal = 1,
+    Critical = 2,
+}
+
+/// A notification to show to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Short single-line summary.
+    pub summary: String,
+    /// Longer description, may span multiple lines.
+    pub body: String,
+    /// How urgent the notification is.
+    pub urgency: Urgency,
+}
+
+impl Notification {
+    /// Creates a notification with normal urgency.
+    pub fn new(summary: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            summary: summary.into(),
+            body: body.into(),
+            urgency: Urgency::Normal,
+        }
+    }
+
+    /// Creates a notification about an error.
+    pub fn error(summary: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            urgency: Urgency::Critical,
+            ..Self::new(summary, body)
+        }
+    }
+}
+
+/// Something that can show notifications.
+pub trait Notifier: fmt::Debug {
+    /// Shows a notification.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the notification could not be delivered, e.g.
+    /// because no notification service is running.
+    fn notify(&self, notification: &Notification) -> Result<(), String>;
+}
@@ -0,0 +1,213 @@
+use crate::{APP_NAME, Notification, Notifier};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+use zbus::blocking::Connection;
+use zbus::blocking::connection::Builder;
+use zbus::zvariant::Value;
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// How long to wait for the notification daemon, as notifications are sent
+/// from the UI thread.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Sends notifications over the `org.freedesktop.Notifications` D-Bus
+/// interface.
+///
+/// Connects to the session bus on first use, so creating a notifier never
+/// fails, and reconnects after a failed call. Calls give up after a second
+/// so an unresponsive daemon can't freeze the caller.
+#[derive(Debug, Default)]
+pub struct DesktopNotifier {
+    connection: RefCell<Option<Connection>>,
+}
+
+impl DesktopNotifier {
+    /// Creates a notifier that uses the session bus.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a notifier that uses the given connection.
+    pub fn with_connection(connection: Connection) -> Self {
+        Self {
+            connection: RefCell::new(Some(connection)),
+        }
+    }
+
+    fn connection(&self) -> zbus::Result<Connection> {
+        let mut cached = self.connection.borrow_mut();
+        if let Some(connection) = &*cached {
+            return Ok(connection.clone());
+        }
+
+        let connection = Builder::session()?.method_timeout(TIMEOUT).build()?;
+        *cached = Some(connection.clone());
+        Ok(connection)
+    }
+
+    fn send(&self, notification: &Notification) -> zbus::Result<()> {
+        let hints = HashMap::from([("urgency", Value::U8(notification.urgency as u8))]);
+
+        self.connection()?.call_method(
+            Some(DESTINATION),
+            PATH,
+            Some(INTERFACE),
+            "Notify",
+            &(
+                APP_NAME,
+                0u32,
+                "",
+                notification.summary.as_str(),
+                escape(&notification.body).as_str(),
+                Vec::<&str>::new(),
+                hints,
+                -1i32,
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Escapes the characters that daemons supporting body markup would read as
+/// markup.
+fn escape(body: &str) -> String {
+    let mut escaped = String::with_capacity(body.len());
+    for c in body.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, notification: &Notification) -> Result<(), String> {
+        self.send(notification).map_err(|e| {
+            // The notification daemon may have been restarted
+            self.connection.borrow_mut().take();
+            format!("Failed to send notification: {e}")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Urgency;
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use zbus::blocking::MessageIterator;
+    use zbus::zvariant::OwnedValue;
+
+    /// Arguments of the `Notify` method, as defined by the spec.
+    type NotifyArgs = (
+        String,
+        u32,
+        String,
+        String,
+        String,
+        Vec<String>,
+        HashMap<String, OwnedValue>,
+        i32,
+    );
+
+    /// Connects a notifier to a stand-in notification daemon over a
+    /// peer-to-peer connection, returning the calls the daemon receives.
+    fn connect() -> (DesktopNotifier, Receiver<NotifyArgs>) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let (calls, received) = mpsc::channel();
+
+        thread::spawn(move || {
+            let server = Builder::async_io_unix_stream(server_stream)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .build()
+                .unwrap();
+
+            for message in MessageIterator::from(&server) {
+                let Ok(message) = message else { break };
+                let header = message.header();
+                if header.member().is_some_and(|m| m == "Notify") {
+                    calls.send(message.body().deserialize().unwrap()).unwrap();
+                    server.reply(&header, &1u32).unwrap();
+                }
+            }
+        });
+        let client = Builder::async_io_unix_stream(client_stream)
+            .p2p()
+            .build()
+            .unwrap();
+
+        (DesktopNotifier::with_connection(client), received)
+    }
+
+    #[test]
+    fn test_notify_sends_to_daemon() {
+        let (notifier, received) = connect();
+
+        notifier
+            .notify(&Notification::error(
+                "Failed to launch 'Web'",
+                "No terminal found",
+            ))
+            .unwrap();
+
+        let (app_name, _, _, summary, body, actions, hints, timeout) = received.recv().unwrap();
+        assert_eq!(app_name, APP_NAME);
+        assert_eq!(summary, "Failed to launch 'Web'");
+        assert_eq!(body, "No terminal found");
+        assert!(actions.is_empty());
+        assert_eq!(
+            u8::try_from(&hints["urgency"]).unwrap(),
+            Urgency::Critical as u8
+        );
+        assert_eq!(timeout, -1);
+    }
+
+    #[test]
+    fn test_notify_escapes_body_markup() {
+        let (notifier, received) = connect();
+
+        notifier
+            .notify(&Notification::new("Done", "exit <1> & <b>done</b>"))
+            .unwrap();
+
+        let (_, _, _, summary, body, ..) = received.recv().unwrap();
+        assert_eq!(summary, "Done");
+        assert_eq!(body, "exit &lt;1&gt; &amp; &lt;b&gt;done&lt;/b&gt;");
+    }
+
+    #[test]
+    fn test_notify_fails_without_daemon() {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            Builder::async_io_unix_stream(server_stream)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .build()
+                .unwrap()
+        });
+        let client = Builder::async_io_unix_stream(client_stream)
+            .p2p()
+            .build()
+            .unwrap();
+        // The daemon goes away after the handshake
+        drop(server.join().unwrap());
+
+        let notifier = DesktopNotifier::with_connection(client);
+        assert!(notifier.notify(&Notification::new("a", "b")).is_err());
+        // The broken connection is dropped so the next call reconnects
+        assert!(notifier.connection.borrow().is_none());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{APP_NAME, Notification, Notifier};
+use std::process::Command;
+
+/// Shows notifications through Notification Center via `osascript`.
+#[derive(Debug, Default)]
+pub struct DesktopNotifier;
+
+impl DesktopNotifier {
+    /// Creates a notifier.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, notification: &Notification) -> Result<(), String> {
+        let script = format!(
+            "display notification {} with title {} subtitle {}",
+            quote(&notification.body),
+            quote(APP_NAME),
+            quote(&notification.summary),
+        );
+
+        let status = Command::new("osascript")
+            .args(["-e", &script])
+            .status()
+            .map_err(|e| format!("Failed to run osascript: {e}"))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("osascript failed: {status}"))
+        }
+    }
+}
+
+/// Quotes a string as an AppleScript string literal.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
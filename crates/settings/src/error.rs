@@ -1,11 +1,39 @@
 use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 // ============================================================================
 // Validation Types
 // ============================================================================
 
+/// A 1-based line and column position in a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column number in characters, starting at 1.
+    pub column: usize,
+}
+
+impl Location {
+    /// Computes the location of a byte offset within `source`.
+    pub(crate) fn from_offset(source: &str, offset: usize) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
 /// A validation error with path and message.
 #[derive(Debug, Clone)]
 pub struct ValidationError {
@@ -13,15 +41,25 @@ pub struct ValidationError {
     pub path: String,
     /// Human-readable error description.
     pub message: String,
+    /// Position in the source file, if it could be determined.
+    pub location: Option<Location>,
+    /// Likely intended value for a misspelled key.
+    pub suggestion: Option<String>,
 }
 
 impl fmt::Display for ValidationError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.path.is_empty() {
-            write!(f, "{}", self.message)
-        } else {
-            write!(f, "{}: {}", self.path, self.message)
+        if let Some(location) = &self.location {
+            write!(f, "{location}: ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
         }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{suggestion}'?)")?;
+        }
+        Ok(())
     }
 }
 
@@ -34,6 +72,96 @@ pub enum ValidationResult {
     Invalid(Vec<ValidationError>),
 }
 
+// ============================================================================
+// Diagnostics
+// ============================================================================
+
+/// How much of a config source a [`Diagnostic`] caused to be skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// An invalid entry or key was skipped; the rest of the file was used.
+    Warning,
+    /// A whole file could not be used.
+    Error,
+}
+
+/// A problem found while loading settings leniently.
+///
+/// See [`Settings::load_lenient()`](crate::Settings::load_lenient).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How much was skipped because of the problem.
+    pub severity: Severity,
+    /// The file the problem was found in, if known.
+    pub file: Option<PathBuf>,
+    /// Position in `file`, if it could be determined.
+    pub location: Option<Location>,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a warning for an invalid part of `file` that was skipped.
+    pub(crate) fn skipped(file: &Path, error: ValidationError) -> Self {
+        let location = error.location;
+        Self {
+            severity: Severity::Warning,
+            file: Some(file.to_path_buf()),
+            location,
+            message: ValidationError {
+                location: None,
+                ..error
+            }
+            .to_string(),
+        }
+    }
+
+    /// Creates an error for a file that could not be used at all.
+    ///
+    /// Errors in included files are reported against the included file.
+    pub fn from_error(file: Option<&Path>, error: &SettingsError) -> Self {
+        let (file, location, message) = match error {
+            SettingsError::Fragment { path, source } => {
+                return Self::from_error(Some(path), source);
+            }
+            SettingsError::ConfigParse(e) if e.line() > 0 => {
+                // The position is reported separately
+                let message = e.to_string();
+                let suffix = format!(" at line {} column {}", e.line(), e.column());
+                let message = message.strip_suffix(&suffix).unwrap_or(&message);
+                (
+                    file,
+                    Some(Location {
+                        line: e.line(),
+                        column: e.column(),
+                    }),
+                    format!("invalid JSON: {message}"),
+                )
+            }
+            e => (file, None, e.to_string()),
+        };
+
+        Self {
+            severity: Severity::Error,
+            file: file.map(Path::to_path_buf),
+            location,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, "{location}: ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
 // ============================================================================
 // Settings Error
 // ============================================================================
@@ -53,15 +181,52 @@ pub enum SettingsError {
     #[error("invalid JSON: {0}")]
     ConfigParse(#[from] serde_json::Error),
 
+    /// Config TOML parse error.
+    #[error("invalid TOML: {0}")]
+    TomlParse(#[from] toml::de::Error),
+
+    /// Config YAML parse error.
+    #[error("invalid YAML: {0}")]
+    YamlParse(#[from] serde_yaml_ng::Error),
+
     /// Config schema validation error.
     #[error("config validation failed: {}", format_validation_errors(.0))]
     ConfigValidation(Vec<ValidationError>),
 
+    /// Error in a file pulled in via `include` or the fragment directory.
+    #[error("{}: {source}", path.display())]
+    Fragment {
+        /// The included file the error occurred in.
+        path: PathBuf,
+        /// The underlying error.
+        source: Box<SettingsError>,
+    },
+
+    /// Config files include each other in a cycle.
+    #[error("include cycle: {}", format_paths(.0))]
+    IncludeCycle(Vec<PathBuf>),
+
+    /// An include pattern is not a valid glob.
+    #[error("invalid include pattern '{pattern}': {message}")]
+    IncludePattern { pattern: String, message: String },
+
+    /// A `.env` file named in `env_file` can't be read or parsed.
+    #[error("invalid env file {}: {message}", path.display())]
+    EnvFile { path: PathBuf, message: String },
+
     /// SSH config parse error (fatal - user should fix their SSH config).
     #[error("failed to parse SSH config: {0}")]
     SshParse(String),
 }
 
+fn format_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
 fn format_validation_errors(errors: &[ValidationError]) -> String {
     errors
         .iter()
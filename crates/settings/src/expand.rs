@@ -0,0 +1,122 @@
+//! Path expansion helpers for user-supplied config values.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Expands environment variables, then a leading `~`.
+///
+/// See [`expand_vars()`] and [`expand_home()`].
+pub(crate) fn expand_path(path: &str) -> PathBuf {
+    expand_home(&expand_vars(path, |name| env::var(name).ok()))
+}
+
+/// Expands `$NAME` and `${NAME}` references using `lookup`.
+///
+/// Like in a shell, unset variables expand to an empty string. A `$` that
+/// isn't followed by a variable name is kept as is.
+pub(crate) fn expand_vars(s: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after),
+            }
+        } else {
+            // Names start with a letter or underscore, like in a shell
+            let end = after
+                .char_indices()
+                .find(|&(i, c)| {
+                    !(c.is_ascii_alphabetic() || c == '_' || (i > 0 && c.is_ascii_digit()))
+                })
+                .map_or(after.len(), |(i, _)| i);
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            out.push('$');
+            rest = after;
+        } else {
+            out.push_str(&lookup(name).unwrap_or_default());
+            rest = remainder;
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Expands a leading `~` to the home directory.
+///
+/// Paths without a leading `~`, and all paths when the home directory is
+/// unknown, are returned unchanged.
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with('/') => &rest[1..],
+        _ => return PathBuf::from(path),
+    };
+
+    match dirs::home_dir() {
+        Some(home) if rest.is_empty() => home,
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_home_prefix() {
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_home("~"), home);
+            assert_eq!(expand_home("~/src/infra"), home.join("src/infra"));
+        }
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PROJECTS" => Some("/home/me/src".to_string()),
+            "ENV" => Some("prod".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_vars() {
+        assert_eq!(expand_vars("$PROJECTS/infra", lookup), "/home/me/src/infra");
+        assert_eq!(
+            expand_vars("${PROJECTS}/infra-$ENV", lookup),
+            "/home/me/src/infra-prod"
+        );
+        assert_eq!(expand_vars("/a/${ENV}suffix", lookup), "/a/prodsuffix");
+    }
+
+    #[test]
+    fn test_expand_vars_unset_and_literal() {
+        assert_eq!(expand_vars("/a/$MISSING/b", lookup), "/a//b");
+        assert_eq!(expand_vars("cost: $5 or $", lookup), "cost: $5 or $");
+        assert_eq!(expand_vars("${unclosed", lookup), "${unclosed");
+    }
+
+    #[test]
+    fn test_expand_path_combines() {
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_path("~/$NOT_SET_XSHUTTLE"), home);
+        }
+    }
+
+    #[test]
+    fn test_expand_home_untouched() {
+        assert_eq!(expand_home("/etc/hosts"), PathBuf::from("/etc/hosts"));
+        assert_eq!(expand_home("relative/~"), PathBuf::from("relative/~"));
+        assert_eq!(expand_home("~other/dir"), PathBuf::from("~other/dir"));
+    }
+}
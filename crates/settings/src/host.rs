@@ -11,9 +11,32 @@ pub struct Host {
 
 impl Host {
     /// Returns the command to execute: `ssh {hostname}`.
+    ///
+    /// The hostname is quoted, so one from a crafted SSH config can't run
+    /// other commands.
     #[must_use]
     pub fn command(&self) -> String {
-        format!("ssh {}", self.hostname)
+        self.ssh(&[])
+    }
+
+    /// Returns a command that attaches to the tmux session `session` on the
+    /// host, creating it if needed: `ssh -t {hostname} 'tmux new -A -s {session}'`.
+    #[must_use]
+    pub fn reattach_command(&self, session: &str) -> String {
+        // ssh hands the remote command to the remote shell as one line
+        let tmux = shell_words::join(["tmux", "new", "-A", "-s", session]);
+        format!("{} {}", self.ssh(&["-t"]), shell_words::quote(&tmux))
+    }
+
+    fn ssh(&self, options: &[&str]) -> String {
+        let mut words = vec!["ssh"];
+        words.extend(options);
+        // Otherwise ssh would take it for an option
+        if self.hostname.starts_with('-') {
+            words.push("--");
+        }
+        words.push(&self.hostname);
+        shell_words::join(words)
     }
 }
 
@@ -29,6 +52,34 @@ mod tests {
         assert_eq!(host.command(), "ssh prod-server");
     }
 
+    #[test]
+    fn test_host_command_quotes_hostname() {
+        let host = Host {
+            hostname: "web;touch /tmp/pwned".into(),
+        };
+        assert_eq!(host.command(), "ssh 'web;touch /tmp/pwned'");
+
+        let host = Host {
+            hostname: "-oProxyCommand=sh".into(),
+        };
+        assert_eq!(host.command(), "ssh -- '-oProxyCommand=sh'");
+    }
+
+    #[test]
+    fn test_host_reattach_command() {
+        let host = Host {
+            hostname: "prod-server".into(),
+        };
+        assert_eq!(
+            host.reattach_command("main"),
+            "ssh -t prod-server 'tmux new -A -s main'"
+        );
+        assert_eq!(
+            host.reattach_command("my work"),
+            r"ssh -t prod-server 'tmux new -A -s '\''my work'\'''"
+        );
+    }
+
     #[test]
     fn test_host_clone() {
         let host = Host {
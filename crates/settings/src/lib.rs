@@ -1,13 +1,22 @@
 mod error;
+mod expand;
 mod host;
 mod loaders;
 mod nodes;
 mod settings;
+mod suggest;
+mod template;
 mod types;
+mod watcher;
 
-pub use error::{SettingsError, ValidationError, ValidationResult};
+pub use error::{Diagnostic, Location, SettingsError, Severity, ValidationError, ValidationResult};
 pub use host::Host;
 pub use loaders::config::{schema, validate};
 pub use nodes::{Node, NodeId, Nodes};
 pub use settings::Settings;
-pub use types::{Action, Entry, Group};
+pub use suggest::did_you_mean;
+pub use template::{Placeholder, Template};
+pub use types::{
+    Action, Confirm, CustomTerminal, Defaults, Entry, Group, KeepOpen, Mode, OpenIn, Tmux,
+};
+pub use watcher::Watcher;
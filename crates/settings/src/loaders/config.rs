@@ -1,8 +1,19 @@
-use crate::error::{SettingsError, ValidationError, ValidationResult};
-use crate::types::Entry;
+use crate::error::{Diagnostic, SettingsError, ValidationError, ValidationResult};
+use crate::loaders::format::Format;
+use crate::loaders::include::{Loader, fragments_dir};
+use crate::loaders::location::Source;
+use crate::loaders::prune;
+use crate::suggest::did_you_mean;
+use crate::template::Template;
+use crate::types::{CustomTerminal, Group, KeepOpen, OpenIn, Tmux};
+use crate::types::{Entry, one_or_many, some_one_or_many};
 use jsonschema::Validator;
+use jsonschema::error::ValidationErrorKind;
 use serde::Deserialize;
 use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -13,9 +24,101 @@ const SCHEMA_JSON: &str = include_str!("../../../../assets/xshuttle.schema.json"
 /// All fields are optional - defaults are applied by the Settings struct.
 #[derive(Debug, Default, Deserialize)]
 pub(crate) struct ConfigContent {
-    pub terminal: Option<String>,
+    /// Terminals to try in order.
+    #[serde(default, deserialize_with = "some_one_or_many")]
+    pub terminal: Option<Vec<String>>,
+    /// Terminals defined in the config, by name.
+    #[serde(default)]
+    pub terminals: BTreeMap<String, CustomTerminal>,
+    pub keep_open: Option<KeepOpen>,
+    pub open_in: Option<OpenIn>,
+    pub tmux: Option<Tmux>,
+    pub shell: Option<String>,
+    pub login_shell: Option<bool>,
     pub editor: Option<String>,
+    pub notifications: Option<bool>,
     pub actions: Option<Vec<Entry>>,
+    /// Files and globs to merge actions from, resolved by the loader.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub include: Vec<String>,
+    /// Canonical paths of all files this content was loaded from.
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+impl ConfigContent {
+    /// Merges content from an included file into this one.
+    ///
+    /// Actions are appended; scalars and terminals are only taken if not
+    /// already set.
+    pub fn absorb(&mut self, other: ConfigContent) {
+        self.terminal = self.terminal.take().or(other.terminal);
+        for (name, terminal) in other.terminals {
+            self.terminals.entry(name).or_insert(terminal);
+        }
+        self.keep_open = self.keep_open.or(other.keep_open);
+        self.open_in = self.open_in.or(other.open_in);
+        self.tmux = self.tmux.take().or(other.tmux);
+        self.shell = self.shell.take().or(other.shell);
+        self.login_shell = self.login_shell.or(other.login_shell);
+        self.editor = self.editor.take().or(other.editor);
+        self.notifications = self.notifications.or(other.notifications);
+
+        if let Some(actions) = other.actions {
+            self.actions.get_or_insert_default().extend(actions);
+        }
+    }
+
+    /// Layers a local override file over this content.
+    ///
+    /// Scalars and terminals set in `overlay` win. Actions are merged with
+    /// [`merge_entries()`].
+    pub fn overlay(&mut self, overlay: ConfigContent) {
+        self.terminal = overlay.terminal.or(self.terminal.take());
+        self.terminals.extend(overlay.terminals);
+        self.keep_open = overlay.keep_open.or(self.keep_open);
+        self.open_in = overlay.open_in.or(self.open_in);
+        self.tmux = overlay.tmux.or(self.tmux.take());
+        self.shell = overlay.shell.or(self.shell.take());
+        self.login_shell = overlay.login_shell.or(self.login_shell);
+        self.editor = overlay.editor.or(self.editor.take());
+        self.notifications = overlay.notifications.or(self.notifications);
+
+        if let Some(actions) = overlay.actions {
+            merge_entries(self.actions.get_or_insert_default(), actions);
+        }
+    }
+}
+
+/// Merges `overlay` entries into `base`, level by level.
+///
+/// - A group named like a group in `base` is merged into it recursively,
+///   along with its defaults.
+/// - An action named like an action in `base` replaces it in place.
+/// - Everything else, including an action named like a group or vice versa,
+///   is appended.
+fn merge_entries(base: &mut Vec<Entry>, overlay: Vec<Entry>) {
+    for entry in overlay {
+        let existing = base.iter_mut().find(|e| match (e, &entry) {
+            (Entry::Action(a), Entry::Action(b)) => a.name == b.name,
+            (Entry::Group(a), Entry::Group(b)) => a.name == b.name,
+            _ => false,
+        });
+
+        match (existing, entry) {
+            (
+                Some(Entry::Group(Group {
+                    entries, defaults, ..
+                })),
+                Entry::Group(group),
+            ) => {
+                defaults.overlay(group.defaults);
+                merge_entries(entries, group.entries);
+            }
+            (Some(existing), entry) => *existing = entry,
+            (None, entry) => base.push(entry),
+        }
+    }
 }
 
 // ============================================================================
@@ -29,69 +132,264 @@ pub fn schema() -> &'static str {
 
 /// Validates a JSON value against the config schema.
 ///
+/// Misspelled keys come with a suggestion for the intended key.
+pub fn validate(value: &Value) -> ValidationResult {
+    let errors = check(value, None);
+
+    if errors.is_empty() {
+        ValidationResult::Valid
+    } else {
+        ValidationResult::Invalid(errors)
+    }
+}
+
+/// Collects schema validation errors for a value.
+///
+/// If `source` is given, each error is mapped to its line and column in it.
+///
 /// # Panics
 ///
 /// Panics if the embedded schema is invalid JSON or not a valid JSON Schema.
 /// This should never happen as the schema is compile-time embedded.
-pub fn validate(value: &Value) -> ValidationResult {
+fn check(value: &Value, source: Option<&Source>) -> Vec<ValidationError> {
     let schema: Value =
         serde_json::from_str(SCHEMA_JSON).expect("embedded schema should be valid JSON");
 
     let validator = Validator::new(&schema).expect("embedded schema should be a valid JSON Schema");
 
-    let errors: Vec<ValidationError> = validator
+    let mut known_keys = Vec::new();
+    collect_property_names(&schema, &mut known_keys);
+
+    let mut errors: Vec<ValidationError> = validator
         .iter_errors(value)
-        .map(|e| ValidationError {
-            path: e.instance_path.to_string(),
-            message: e.to_string(),
+        .map(|e| {
+            let path = e.instance_path.to_string();
+
+            // Point at the offending key itself rather than its object
+            let (pointer, suggestion) = match &e.kind {
+                ValidationErrorKind::AdditionalProperties { unexpected } => (
+                    unexpected
+                        .first()
+                        .map_or_else(|| path.clone(), |key| format!("{path}/{}", escape(key))),
+                    unexpected
+                        .iter()
+                        .find_map(|key| did_you_mean(key, known_keys.iter().copied()))
+                        .map(str::to_string),
+                ),
+                _ => (path.clone(), None),
+            };
+
+            ValidationError {
+                location: source.and_then(|source| source.locate(&pointer)),
+                path,
+                message: e.to_string(),
+                suggestion,
+            }
         })
         .collect();
 
-    if errors.is_empty() {
-        ValidationResult::Valid
-    } else {
-        ValidationResult::Invalid(errors)
+    if let Some(entries) = value.get("actions") {
+        check_templates(entries, "/actions", source, &mut errors);
+    }
+    errors
+}
+
+/// Collects errors for action commands with malformed placeholders.
+///
+/// Runs on the raw value, so that these are reported and pruned like schema
+/// errors. Entries the schema rejects are skipped or reported twice, which
+/// is harmless.
+fn check_templates(
+    entries: &Value,
+    pointer: &str,
+    source: Option<&Source>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let Some(entries) = entries.as_array() else {
+        return;
+    };
+
+    for (i, entry) in entries.iter().enumerate() {
+        let Some(map) = entry.as_object() else {
+            continue;
+        };
+
+        if let Some(Value::String(cmd)) = map.get("cmd") {
+            if let Err(message) = Template::parse(cmd) {
+                let path = format!("{pointer}/{i}/cmd");
+                errors.push(ValidationError {
+                    location: source.and_then(|source| source.locate(&path)),
+                    path,
+                    message,
+                    suggestion: None,
+                });
+            }
+            continue;
+        }
+
+        // Either form of group, whose entries are the one array it holds
+        for (key, children) in map.iter().filter(|(_, v)| v.is_array()) {
+            let pointer = format!("{pointer}/{i}/{}", escape(key));
+            check_templates(children, &pointer, source, errors);
+        }
+    }
+}
+
+/// Collects the names of all properties defined anywhere in a schema.
+fn collect_property_names<'a>(schema: &'a Value, names: &mut Vec<&'a str>) {
+    match schema {
+        Value::Object(map) => {
+            if let Some(Value::Object(properties)) = map.get("properties") {
+                names.extend(properties.keys().map(String::as_str));
+            }
+            for child in map.values() {
+                collect_property_names(child, names);
+            }
+        }
+        Value::Array(items) => {
+            for child in items {
+                collect_property_names(child, names);
+            }
+        }
+        _ => {}
     }
 }
 
+/// Escapes a key for use as a JSON pointer segment.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
 // ============================================================================
 // Config Loading
 // ============================================================================
 
-/// Returns the default config file path (~/.xshuttle.json).
+/// Environment variable overriding the config file location.
+pub const CONFIG_ENV: &str = "XSHUTTLE_CONFIG";
+
+/// Returns the config file path.
+///
+/// Lookup order:
+/// 1. `$XSHUTTLE_CONFIG`, if set and non-empty
+/// 2. `$XDG_CONFIG_HOME/xshuttle/config.{json,toml,yaml,yml}` (`~/.config` if
+///    unset), the first that exists
+/// 3. `~/.xshuttle.{json,toml,yaml,yml}`, the legacy location, the first that exists
+/// 4. `~/.xshuttle.json`, used when no config exists yet
 pub fn config_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|home| home.join(".xshuttle.json"))
+    resolve_config_path(
+        env::var_os(CONFIG_ENV),
+        env::var_os("XDG_CONFIG_HOME"),
+        dirs::home_dir(),
+    )
+}
+
+fn resolve_config_path(
+    config_env: Option<OsString>,
+    xdg_config_home: Option<OsString>,
+    home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    if let Some(path) = config_env.filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+
+    let home = home?;
+
+    // Relative XDG paths are invalid per spec and must be ignored
+    let xdg_config_home = xdg_config_home
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| home.join(".config"));
+
+    let xdg_dir = xdg_config_home.join("xshuttle");
+    let candidates = Format::EXTENSIONS
+        .iter()
+        .map(|ext| xdg_dir.join(format!("config.{ext}")))
+        .chain(
+            Format::EXTENSIONS
+                .iter()
+                .map(|ext| home.join(format!(".xshuttle.{ext}"))),
+        );
+
+    for candidate in candidates {
+        if candidate.exists() {
+            return Some(candidate);
+        }
+    }
+
+    Some(home.join(".xshuttle.json"))
 }
 
 /// Ensures the config file exists, creating a default one if missing.
 ///
 /// # Errors
 ///
-/// Returns an error if the home directory cannot be determined or if
+/// Returns an error if the parent directory cannot be created or if
 /// writing the default config file fails.
-pub fn ensure_config_exists() -> Result<PathBuf, SettingsError> {
-    let path = config_path().ok_or(SettingsError::NoHomeDir)?;
-
+pub fn ensure_config_exists(path: &Path) -> Result<(), SettingsError> {
     if !path.exists() {
-        fs::write(&path, DEFAULT_JSON)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, default_config(Format::from_path(path)))?;
         eprintln!("Created default config at {}", path.display());
     }
 
-    Ok(path)
+    Ok(())
+}
+
+/// Returns the default config in the given format.
+///
+/// The JSON default is written as is, with its comments. Other formats get
+/// the same settings converted, with a pointer to the documentation and the
+/// schema directive their editor tooling understands.
+///
+/// # Panics
+///
+/// Panics if the embedded default config can't be converted. This should
+/// never happen as it is compile-time embedded and tested.
+fn default_config(format: Format) -> String {
+    const DOCS: &str = "# See https://github.com/athopen/xshuttle#configuration for all options.";
+
+    if format == Format::Json {
+        return DEFAULT_JSON.to_string();
+    }
+
+    let mut value = Format::Json
+        .parse(DEFAULT_JSON)
+        .expect("default config should be valid JSON");
+    let schema = value
+        .as_object_mut()
+        .and_then(|map| map.remove("$schema"))
+        .and_then(|url| url.as_str().map(str::to_string))
+        .unwrap_or_default();
+
+    match format {
+        Format::Toml => format!(
+            "#:schema {schema}\n{DOCS}\n\n{}",
+            toml::to_string(&value).expect("default config should convert to TOML")
+        ),
+        Format::Yaml => format!(
+            "# yaml-language-server: $schema={schema}\n{DOCS}\n\n{}",
+            serde_yaml_ng::to_string(&value).expect("default config should convert to YAML")
+        ),
+        Format::Json => unreachable!(),
+    }
 }
 
-/// Loads config content from a string.
+/// Loads config content from a string in the given format.
 ///
-/// Validates against the schema first, then deserializes.
+/// The document is converted to a JSON value, validated against the schema,
+/// then deserialized, so all formats share the same rules.
 ///
 /// # Errors
 ///
-/// Returns an error if the JSON is invalid or fails schema validation.
-pub fn load_from_str(s: &str) -> Result<ConfigContent, SettingsError> {
-    let value: Value = serde_json::from_str(s).map_err(SettingsError::ConfigParse)?;
+/// Returns an error if the document is unparseable or fails schema validation.
+pub fn load_from_str_as(s: &str, format: Format) -> Result<ConfigContent, SettingsError> {
+    let value = format.parse(s)?;
 
-    // Validate against schema
-    if let ValidationResult::Invalid(errors) = validate(&value) {
+    // Validate against schema, locating errors in the source
+    let errors = check(&value, Some(&Source::new(format, s)));
+    if !errors.is_empty() {
         return Err(SettingsError::ConfigValidation(errors));
     }
 
@@ -99,36 +397,124 @@ pub fn load_from_str(s: &str) -> Result<ConfigContent, SettingsError> {
     serde_json::from_value(value).map_err(SettingsError::ConfigParse)
 }
 
-/// Loads config content from a specific path.
+/// Loads config content from a string, skipping invalid parts.
+///
+/// Like [`load_from_str_as()`], but entries and keys that fail schema
+/// validation are removed (see [`prune`]) instead of failing the whole
+/// document. Returns the valid rest together with the errors that caused
+/// parts to be skipped.
 ///
 /// # Errors
 ///
-/// Returns an error if the file cannot be read or the JSON is unparseable.
-pub fn load_from_path(path: &Path) -> Result<ConfigContent, SettingsError> {
-    let contents = fs::read_to_string(path)?;
-    load_from_str(&contents)
+/// Returns an error if the document is unparseable.
+pub(crate) fn load_from_str_lenient(
+    s: &str,
+    format: Format,
+) -> Result<(ConfigContent, Vec<ValidationError>), SettingsError> {
+    let mut value = format.parse(s)?;
+
+    let schema: Value =
+        serde_json::from_str(SCHEMA_JSON).expect("embedded schema should be valid JSON");
+    let known_keys: Vec<&str> = schema["properties"]
+        .as_object()
+        .map(|properties| properties.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+
+    // Only the first pass matches the source text; later passes run on the
+    // pruned value, where array indices have shifted
+    let source = Source::new(format, s);
+    let mut source = Some(&source);
+    let mut skipped = Vec::new();
+
+    loop {
+        let errors = check(&value, source.take());
+        if errors.is_empty() {
+            break;
+        }
+        if !prune::prune(&mut value, &errors, &known_keys) {
+            return Err(SettingsError::ConfigValidation(errors));
+        }
+        skipped.extend(errors);
+    }
+
+    let content = serde_json::from_value(value).map_err(SettingsError::ConfigParse)?;
+    Ok((content, skipped))
+}
+
+/// Returns the local override file for a config file.
+///
+/// `~/.xshuttle.json` → `~/.xshuttle.local.json`,
+/// `~/.config/xshuttle/config.json` → `~/.config/xshuttle/config.local.json`.
+pub fn local_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{stem}.local.{}", ext.to_string_lossy())),
+        None => path.with_file_name(format!("{stem}.local")),
+    }
 }
 
-/// Loads config content from the default path (~/.xshuttle.json).
+/// Loads config content from the given path, its includes, its fragment
+/// directory and its local override file.
 ///
-/// Returns `None` if the config file doesn't exist (caller should use defaults).
+/// Returns `None` if neither the config file nor its local override or
+/// fragment directory exists (caller should use defaults).
 ///
 /// # Errors
 ///
-/// Returns an error if the home directory cannot be determined or the JSON is unparseable.
-pub fn load() -> Result<Option<ConfigContent>, SettingsError> {
-    let path = config_path().ok_or(SettingsError::NoHomeDir)?;
+/// Returns an error if any of the files cannot be read or is unparseable,
+/// or if includes form a cycle.
+pub fn load(path: &Path) -> Result<Option<ConfigContent>, SettingsError> {
+    load_with(path, &mut Loader::default())
+}
 
-    if !path.exists() {
+/// Loads config content from the config file at `path`, skipping invalid
+/// parts.
+///
+/// Invalid entries and keys are dropped, and files that can't be read or
+/// parsed are skipped, while the rest is loaded as with [`load()`]. Every
+/// problem is returned as a [`Diagnostic`].
+pub fn load_lenient(path: &Path) -> (Option<ConfigContent>, Vec<Diagnostic>) {
+    let mut loader = Loader::lenient();
+    let result = load_with(path, &mut loader);
+    let mut diagnostics = loader.take_diagnostics();
+
+    let content = result.unwrap_or_else(|e| {
+        diagnostics.push(Diagnostic::from_error(Some(path), &e));
+        None
+    });
+    (content, diagnostics)
+}
+
+fn load_with(path: &Path, loader: &mut Loader) -> Result<Option<ConfigContent>, SettingsError> {
+    // The local file and fragments apply over the defaults on their own
+    let local = local_path(path);
+    if !path.exists() && !local.exists() && !fragments_dir(path).is_dir() {
         return Ok(None);
     }
 
-    load_from_path(&path).map(Some)
+    // Both apply once, in their own place, whatever globs match them
+    loader.exclude(path);
+    loader.exclude(&local);
+    let mut content = loader.load_main(path)?;
+
+    if local.exists()
+        && let Some(overlay) = loader.load_included(&local)?
+    {
+        content.overlay(overlay);
+    }
+
+    content.sources = loader.take_sources();
+    Ok(Some(content))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Defaults;
+
+    fn load_from_str(s: &str) -> Result<ConfigContent, SettingsError> {
+        load_from_str_as(s, Format::Json)
+    }
 
     // Schema validation tests
     #[test]
@@ -184,6 +570,736 @@ mod tests {
         assert!(matches!(validate(&value), ValidationResult::Valid));
     }
 
+    #[test]
+    fn test_groups_named_like_action_keys() {
+        let config = r#"{
+            "actions": [
+                {"name": [{"name": "Server", "cmd": "ssh server"}]},
+                {"cmd": [{"name": "Db", "cmd": "ssh db"}]}
+            ]
+        }"#;
+        let value: Value = serde_json::from_str(config).unwrap();
+        assert!(matches!(validate(&value), ValidationResult::Valid));
+
+        let (content, skipped) = load_from_str_lenient(config, Format::Json).unwrap();
+        assert!(skipped.is_empty(), "{skipped:?}");
+        assert_eq!(
+            summary(&content.actions.unwrap()),
+            vec!["name[Server=ssh server]", "cmd[Db=ssh db]"]
+        );
+    }
+
+    // Config path tests
+    fn temp_home(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("xshuttle-config-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_config_path_env_override() {
+        let path = resolve_config_path(
+            Some("/etc/xshuttle.json".into()),
+            None,
+            Some(PathBuf::from("/home/user")),
+        );
+        assert_eq!(path, Some(PathBuf::from("/etc/xshuttle.json")));
+    }
+
+    #[test]
+    fn test_config_path_empty_env_ignored() {
+        let path = resolve_config_path(Some("".into()), None, Some(PathBuf::from("/home/user")));
+        assert_eq!(path, Some(PathBuf::from("/home/user/.xshuttle.json")));
+    }
+
+    #[test]
+    fn test_config_path_default_when_missing() {
+        let home = temp_home("missing");
+        let path = resolve_config_path(None, None, Some(home.clone()));
+        assert_eq!(path, Some(home.join(".xshuttle.json")));
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_legacy_fallback() {
+        let home = temp_home("legacy");
+        fs::create_dir_all(home.join(".config").join("xshuttle")).unwrap();
+        fs::write(home.join(".xshuttle.json"), "{}").unwrap();
+
+        let path = resolve_config_path(None, None, Some(home.clone()));
+        assert_eq!(path, Some(home.join(".xshuttle.json")));
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_order() {
+        let home = temp_home("order");
+        let xdg_path = home.join(".config").join("xshuttle").join("config.json");
+        fs::create_dir_all(xdg_path.parent().unwrap()).unwrap();
+        fs::write(&xdg_path, "{}").unwrap();
+        fs::write(home.join(".xshuttle.json"), "{}").unwrap();
+
+        // The XDG location wins over the legacy one
+        let path = resolve_config_path(None, None, Some(home.clone()));
+        assert_eq!(path, Some(xdg_path));
+
+        // The environment variable wins over both
+        let path = resolve_config_path(Some("/etc/xshuttle.json".into()), None, Some(home.clone()));
+        assert_eq!(path, Some(PathBuf::from("/etc/xshuttle.json")));
+
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_xdg_default_dir() {
+        let home = temp_home("xdg-default");
+        let xdg_path = home.join(".config").join("xshuttle").join("config.json");
+        fs::create_dir_all(xdg_path.parent().unwrap()).unwrap();
+        fs::write(&xdg_path, "{}").unwrap();
+
+        let path = resolve_config_path(None, None, Some(home.clone()));
+        assert_eq!(path, Some(xdg_path));
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_xdg_config_home() {
+        let home = temp_home("xdg-home");
+        let xdg_home = home.join("dotfiles");
+        let xdg_path = xdg_home.join("xshuttle").join("config.json");
+        fs::create_dir_all(xdg_path.parent().unwrap()).unwrap();
+        fs::write(&xdg_path, "{}").unwrap();
+
+        let path = resolve_config_path(None, Some(xdg_home.into()), Some(home.clone()));
+        assert_eq!(path, Some(xdg_path));
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_other_formats() {
+        let home = temp_home("formats");
+        fs::write(home.join(".xshuttle.yaml"), "{}").unwrap();
+
+        let path = resolve_config_path(None, None, Some(home.clone()));
+        assert_eq!(path, Some(home.join(".xshuttle.yaml")));
+
+        let xdg_path = home.join(".config").join("xshuttle").join("config.toml");
+        fs::create_dir_all(xdg_path.parent().unwrap()).unwrap();
+        fs::write(&xdg_path, "").unwrap();
+
+        let path = resolve_config_path(None, None, Some(home.clone()));
+        assert_eq!(path, Some(xdg_path));
+
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_relative_xdg_ignored() {
+        let home = temp_home("xdg-relative");
+        let path = resolve_config_path(None, Some("relative".into()), Some(home.clone()));
+        assert_eq!(path, Some(home.join(".xshuttle.json")));
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_config_path_no_home() {
+        assert_eq!(resolve_config_path(None, None, None), None);
+    }
+
+    #[test]
+    fn test_ensure_config_exists_creates_parent_dirs() {
+        let home = temp_home("ensure");
+        let path = home.join(".config").join("xshuttle").join("config.json");
+
+        ensure_config_exists(&path).unwrap();
+        let content = load(&path).unwrap().unwrap();
+        assert_eq!(content.terminal, Some(vec!["default".to_string()]));
+
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_config_exists_matches_format() {
+        let home = temp_home("ensure-formats");
+        let json = load_from_str(DEFAULT_JSON).unwrap();
+
+        for name in ["config.toml", "config.yaml", "config.yml"] {
+            let path = home.join(name);
+            ensure_config_exists(&path).unwrap();
+
+            let text = fs::read_to_string(&path).unwrap();
+            assert!(text.contains("xshuttle.schema.json"), "{name}: {text}");
+            let content = load(&path).unwrap().unwrap();
+            assert_eq!(content.terminal, json.terminal, "{name}");
+            assert_eq!(content.keep_open, json.keep_open, "{name}");
+            assert_eq!(content.editor, json.editor, "{name}");
+        }
+
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_validate_include() {
+        for config in [
+            r#"{"include": "team.json"}"#,
+            r#"{"include": ["a.json", "*.json"]}"#,
+        ] {
+            let value: Value = serde_json::from_str(config).unwrap();
+            assert!(matches!(validate(&value), ValidationResult::Valid));
+        }
+
+        let value: Value = serde_json::from_str(r#"{"include": 1}"#).unwrap();
+        assert!(matches!(validate(&value), ValidationResult::Invalid(_)));
+    }
+
+    // Local override tests
+    fn action(name: &str, cmd: &str) -> Entry {
+        Entry::Action(crate::types::Action {
+            name: name.into(),
+            cmd: cmd.into(),
+            ..Default::default()
+        })
+    }
+
+    fn group(name: &str, entries: Vec<Entry>) -> Entry {
+        Entry::Group(Group {
+            name: name.into(),
+            defaults: Defaults::default(),
+            entries,
+        })
+    }
+
+    fn summary(entries: &[Entry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|e| match e {
+                Entry::Action(a) => format!("{}={}", a.name, a.cmd),
+                Entry::Group(g) => format!("{}[{}]", g.name, summary(&g.entries).join(",")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(
+            local_path(Path::new("/home/u/.xshuttle.json")),
+            PathBuf::from("/home/u/.xshuttle.local.json")
+        );
+        assert_eq!(
+            local_path(Path::new("/home/u/.config/xshuttle/config.json")),
+            PathBuf::from("/home/u/.config/xshuttle/config.local.json")
+        );
+    }
+
+    #[test]
+    fn test_overlay_scalars_override() {
+        let mut base = load_from_str(r#"{"terminal": "kitty", "editor": "vim"}"#).unwrap();
+        base.overlay(load_from_str(r#"{"terminal": "alacritty"}"#).unwrap());
+
+        assert_eq!(base.terminal, Some(vec!["alacritty".to_string()]));
+        assert_eq!(base.editor.as_deref(), Some("vim"));
+    }
+
+    #[test]
+    fn test_custom_terminals() {
+        let mut base = load_from_str(
+            r#"{"terminals": {
+                "foot": {"bin": "foot", "args": ["--working-directory={cwd}", "sh", "-c", "{cmd}"]},
+                "st": {"bin": "st", "args": ["-e", "sh", "-c", "{cmd}"]}
+            }}"#,
+        )
+        .unwrap();
+        base.overlay(
+            load_from_str(r#"{"terminals": {"st": {"bin": "~/bin/st", "args": ["{cmd}"]}}}"#)
+                .unwrap(),
+        );
+
+        assert_eq!(base.terminals.len(), 2);
+        assert_eq!(base.terminals["foot"].args[0], "--working-directory={cwd}");
+        assert_eq!(base.terminals["st"].bin, "~/bin/st");
+
+        let result = load_from_str(r#"{"terminals": {"foot": {"bin": "foot"}}}"#);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+    }
+
+    #[test]
+    fn test_keep_open() {
+        let mut base = load_from_str(r#"{"keep_open": "pause"}"#).unwrap();
+        assert_eq!(base.keep_open, Some(KeepOpen::Pause));
+
+        base.overlay(load_from_str(r#"{"keep_open": "close"}"#).unwrap());
+        assert_eq!(base.keep_open, Some(KeepOpen::Close));
+
+        let result = load_from_str(r#"{"keep_open": "forever"}"#);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+    }
+
+    #[test]
+    fn test_open_in() {
+        let mut base = load_from_str(r#"{"open_in": "tab"}"#).unwrap();
+        assert_eq!(base.open_in, Some(OpenIn::Tab));
+
+        base.overlay(load_from_str(r#"{"open_in": "window"}"#).unwrap());
+        assert_eq!(base.open_in, Some(OpenIn::Window));
+
+        let result = load_from_str(r#"{"open_in": "pane"}"#);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+    }
+
+    #[test]
+    fn test_tmux() {
+        let mut base =
+            load_from_str(r#"{"terminal": ["tmux", "kitty"], "tmux": {"session": "work"}}"#)
+                .unwrap();
+        let tmux = base.tmux.clone().unwrap();
+        assert_eq!(tmux.session.as_deref(), Some("work"));
+        assert_eq!(tmux.reattach, None);
+
+        base.overlay(
+            load_from_str(r#"{"tmux": {"reattach": "main", "synchronize_panes": false}}"#).unwrap(),
+        );
+        let tmux = base.tmux.unwrap();
+        assert_eq!(tmux.session, None);
+        assert_eq!(tmux.reattach.as_deref(), Some("main"));
+        assert_eq!(tmux.synchronize_panes, Some(false));
+
+        let result = load_from_str(r#"{"tmux": {"sesion": "work"}}"#);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+    }
+
+    #[test]
+    fn test_shell() {
+        let mut base = load_from_str(r#"{"shell": "zsh", "login_shell": true}"#).unwrap();
+        base.overlay(load_from_str(r#"{"shell": "/opt/homebrew/bin/fish"}"#).unwrap());
+        assert_eq!(base.shell.as_deref(), Some("/opt/homebrew/bin/fish"));
+        assert_eq!(base.login_shell, Some(true));
+
+        let result = load_from_str(r#"{"shell": ""}"#);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+    }
+
+    #[test]
+    fn test_notifications_toggle() {
+        let mut base = load_from_str(r#"{"notifications": true}"#).unwrap();
+        base.overlay(load_from_str(r#"{"notifications": false}"#).unwrap());
+        assert_eq!(base.notifications, Some(false));
+
+        assert!(load_from_str(r#"{"notifications": "off"}"#).is_err());
+    }
+
+    #[test]
+    fn test_merge_appends_new_entries() {
+        let mut base = vec![action("Deploy", "deploy.sh")];
+        merge_entries(&mut base, vec![action("Logs", "tail -f log")]);
+
+        assert_eq!(summary(&base), vec!["Deploy=deploy.sh", "Logs=tail -f log"]);
+    }
+
+    #[test]
+    fn test_merge_into_existing_group() {
+        let mut base = vec![group("Prod", vec![action("Web", "ssh web")])];
+        merge_entries(
+            &mut base,
+            vec![group("Prod", vec![action("My box", "ssh mybox")])],
+        );
+
+        assert_eq!(summary(&base), vec!["Prod[Web=ssh web,My box=ssh mybox]"]);
+    }
+
+    #[test]
+    fn test_merge_group_defaults() {
+        let base = load_from_str(
+            r#"{"actions": [{"group": "Prod", "defaults": {"cwd": "~/prod", "env": {"A": "1", "B": "1"}}, "entries": []}]}"#,
+        )
+        .unwrap();
+        let overlay = load_from_str(
+            r#"{"actions": [{"group": "Prod", "defaults": {"env": {"B": "2"}, "terminal": "kitty"}}]}"#,
+        )
+        .unwrap();
+
+        let mut actions = base.actions.unwrap();
+        merge_entries(&mut actions, overlay.actions.unwrap());
+
+        let Entry::Group(group) = &actions[0] else {
+            panic!("Expected group");
+        };
+        assert_eq!(group.defaults.env["A"], "1");
+        assert_eq!(group.defaults.env["B"], "2");
+        assert_eq!(group.defaults.cwd.as_deref(), Some("~/prod"));
+        assert_eq!(group.defaults.terminal, ["kitty"]);
+    }
+
+    #[test]
+    fn test_merge_nested_groups() {
+        let mut base = vec![group(
+            "Prod",
+            vec![group("EU", vec![action("Web", "ssh eu-web")])],
+        )];
+        merge_entries(
+            &mut base,
+            vec![group(
+                "Prod",
+                vec![
+                    group("EU", vec![action("DB", "ssh eu-db")]),
+                    group("US", vec![action("Web", "ssh us-web")]),
+                ],
+            )],
+        );
+
+        assert_eq!(
+            summary(&base),
+            vec!["Prod[EU[Web=ssh eu-web,DB=ssh eu-db],US[Web=ssh us-web]]"]
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicting_action_replaced_in_place() {
+        let mut base = vec![action("Deploy", "deploy.sh"), action("Logs", "tail -f log")];
+        merge_entries(&mut base, vec![action("Deploy", "deploy.sh --dry-run")]);
+
+        assert_eq!(
+            summary(&base),
+            vec!["Deploy=deploy.sh --dry-run", "Logs=tail -f log"]
+        );
+    }
+
+    #[test]
+    fn test_merge_action_and_group_with_same_name_kept() {
+        let mut base = vec![action("Prod", "ssh prod")];
+        merge_entries(
+            &mut base,
+            vec![group("Prod", vec![action("Web", "ssh web")])],
+        );
+
+        assert_eq!(summary(&base), vec!["Prod=ssh prod", "Prod[Web=ssh web]"]);
+    }
+
+    #[test]
+    fn test_load_applies_local_override() {
+        let dir = env::temp_dir().join(format!("xshuttle-local-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(
+            &path,
+            r#"{"terminal": "kitty", "actions": [{"Prod": [{"name": "Web", "cmd": "ssh web"}]}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            local_path(&path),
+            r#"{"terminal": "xterm", "actions": [{"Prod": [{"name": "Mine", "cmd": "ssh mine"}]}]}"#,
+        )
+        .unwrap();
+
+        let content = load(&path).unwrap().unwrap();
+        assert_eq!(content.terminal, Some(vec!["xterm".to_string()]));
+        assert_eq!(
+            summary(&content.actions.unwrap()),
+            vec!["Prod[Web=ssh web,Mine=ssh mine]"]
+        );
+        assert_eq!(content.sources.len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_local_override_without_main_file() {
+        let dir = env::temp_dir().join(format!("xshuttle-local-only-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        let path = dir.join("config.json");
+        fs::write(local_path(&path), r#"{"terminal": "xterm"}"#).unwrap();
+        fs::write(
+            dir.join("conf.d").join("team.json"),
+            r#"{"actions": [{"name": "Web", "cmd": "ssh web"}]}"#,
+        )
+        .unwrap();
+
+        let content = load(&path).unwrap().expect("local file should be loaded");
+        assert_eq!(content.terminal, Some(vec!["xterm".to_string()]));
+        assert_eq!(summary(&content.actions.unwrap()), vec!["Web=ssh web"]);
+        assert_eq!(content.sources.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(load(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_include_glob_skips_main_and_local_files() {
+        let dir = env::temp_dir().join(format!("xshuttle-local-glob-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(
+            &path,
+            r#"{"include": "*.json", "editor": "vim", "actions": [{"Prod": [{"name": "Web", "cmd": "ssh web"}]}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            local_path(&path),
+            r#"{"editor": "nano", "actions": [{"Prod": [{"name": "Db", "cmd": "ssh db"}]}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("team.json"),
+            r#"{"actions": [{"name": "Top", "cmd": "htop"}]}"#,
+        )
+        .unwrap();
+
+        let (content, diagnostics) = load_lenient(&path);
+        let content = content.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(content.editor.as_deref(), Some("nano"));
+        assert_eq!(
+            summary(&content.actions.unwrap()),
+            vec!["Prod[Web=ssh web,Db=ssh db]", "Top=htop"]
+        );
+    }
+
+    // JSONC
+    #[test]
+    fn test_load_jsonc() {
+        let content = load_from_str(
+            r#"{
+                // Comments and trailing commas are allowed
+                "terminal": "kitty", /* inline */
+                "actions": [
+                    {"name": "Deploy", "cmd": "deploy.sh"},
+                ],
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(content.terminal, Some(vec!["kitty".to_string()]));
+        assert_eq!(content.actions.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_jsonc_still_validated() {
+        let result = load_from_str(
+            r#"{
+                // Typo below
+                "termnal": "kitty",
+            }"#,
+        );
+        match result {
+            Err(SettingsError::ConfigValidation(errors)) => {
+                assert!(errors[0].message.contains("termnal"));
+            }
+            _ => panic!("Expected validation error"),
+        }
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let content = load_from_str(DEFAULT_JSON).unwrap();
+        assert_eq!(content.terminal, Some(vec!["default".to_string()]));
+        assert_eq!(content.editor.as_deref(), Some("default"));
+    }
+
+    // Error reporting
+    use crate::error::Location;
+
+    fn validation_errors(s: &str) -> Vec<ValidationError> {
+        match load_from_str(s) {
+            Err(SettingsError::ConfigValidation(errors)) => errors,
+            other => panic!("Expected validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_nested_typo_reports_single_focused_error() {
+        let errors = validation_errors(
+            r#"{
+  "actions": [
+    {"Production": [
+      {"name": "Web", "cmd": "ssh web"},
+      {"nmae": "DB", "cmd": "ssh db"}
+    ]}
+  ]
+}"#,
+        );
+
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors.iter().all(|e| e.path == "/actions/0/Production/1"));
+        assert!(errors.iter().all(|e| !e.message.contains("oneOf")));
+
+        let unexpected = errors
+            .iter()
+            .find(|e| e.message.contains("nmae"))
+            .expect("should report the unexpected key");
+        assert_eq!(unexpected.suggestion.as_deref(), Some("name"));
+        assert_eq!(unexpected.location, Some(Location { line: 5, column: 8 }));
+    }
+
+    #[test]
+    fn test_missing_cmd_located_at_action() {
+        let errors = validation_errors("{\n  \"actions\": [\n    {\"name\": \"Test\"}\n  ]\n}");
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("cmd"));
+        assert_eq!(errors[0].location, Some(Location { line: 3, column: 5 }));
+    }
+
+    #[test]
+    fn test_group_branch_chosen_without_action_keys() {
+        let errors = validation_errors(r#"{"actions": [{"Production": "ssh prod"}]}"#);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/actions/0/Production");
+        assert!(errors[0].message.contains("array"));
+    }
+
+    #[test]
+    fn test_env_validated() {
+        let errors = validation_errors(
+            r#"{"actions": [{"name": "A", "cmd": "a", "env": {"BAD-NAME": "x", "OK": 1}}]}"#,
+        );
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors.iter().all(|e| e.path.starts_with("/actions/0/env")));
+    }
+
+    #[test]
+    fn test_placeholders_validated() {
+        let errors = validation_errors(
+            "{\n  \"actions\": [\n    {\"Prod\": [\n      {\"name\": \"Logs\", \"cmd\": \"kubectl logs {input:Pod\"}\n    ]}\n  ]\n}",
+        );
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].path, "/actions/0/Prod/0/cmd");
+        assert!(errors[0].message.contains("unterminated"));
+        assert_eq!(
+            errors[0].location,
+            Some(Location {
+                line: 4,
+                column: 24
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_placeholder_skips_action() {
+        let (content, skipped) = load_from_str_lenient(
+            r#"{"actions": [
+                {"group": "Prod", "entries": [
+                    {"name": "Bastion", "cmd": "ssh {choice:Region}-bastion"},
+                    {"name": "Logs", "cmd": "kubectl logs {input:Pod}"}
+                ]}
+            ]}"#,
+            Format::Json,
+        )
+        .unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, "/actions/0/entries/0/cmd");
+
+        let Some([Entry::Group(group)]) = content.actions.as_deref() else {
+            panic!("Expected group, got {:?}", content.actions);
+        };
+        assert_eq!(group.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_group_object_typo_suggestion() {
+        let errors = validation_errors(
+            r#"{"actions": [{"group": "Prod", "defaults": {"env_fle": ".env"}, "entries": []}]}"#,
+        );
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].path, "/actions/0/defaults");
+        assert_eq!(errors[0].suggestion.as_deref(), Some("env_file"));
+    }
+
+    #[test]
+    fn test_top_level_typo_suggestion() {
+        let errors = validation_errors("{\n  // comment\n  \"termnial\": \"kitty\"\n}");
+
+        assert_eq!(errors[0].suggestion.as_deref(), Some("terminal"));
+        assert_eq!(errors[0].location, Some(Location { line: 3, column: 3 }));
+        assert_eq!(
+            errors[0].to_string(),
+            "line 3, column 3: Additional properties are not allowed ('termnial' was unexpected) \
+             (did you mean 'terminal'?)"
+        );
+    }
+
+    #[test]
+    fn test_other_formats_have_locations() {
+        let toml = "editor = \"vim\"\n\n[[actions]]\nname = \"Top\"\nnmae = \"x\"\n";
+        let Err(SettingsError::ConfigValidation(errors)) = load_from_str_as(toml, Format::Toml)
+        else {
+            panic!("Expected validation error");
+        };
+        assert_eq!(errors[0].suggestion.as_deref(), Some("name"));
+        assert_eq!(errors[0].location, Some(Location { line: 5, column: 1 }));
+
+        let yaml = "actions:\n  - name: Top\n    cmd: htop\n  - name: Web\n    cmd: 1\n";
+        let Err(SettingsError::ConfigValidation(errors)) = load_from_str_as(yaml, Format::Yaml)
+        else {
+            panic!("Expected validation error");
+        };
+        assert_eq!(errors[0].path, "/actions/1/cmd");
+        assert_eq!(errors[0].location, Some(Location { line: 5, column: 5 }));
+    }
+
+    // Other formats
+    #[test]
+    fn test_load_toml() {
+        let content = load_from_str_as(
+            r#"
+            terminal = "kitty"
+
+            [[actions]]
+            name = "Deploy"
+            cmd = "deploy.sh"
+
+            [[actions]]
+            Production = [
+                { name = "Server", cmd = "ssh server" },
+            ]
+            "#,
+            Format::Toml,
+        )
+        .unwrap();
+
+        assert_eq!(content.terminal, Some(vec!["kitty".to_string()]));
+        let entries = content.actions.unwrap();
+        assert!(matches!(&entries[0], Entry::Action(a) if a.name == "Deploy"));
+        assert!(matches!(&entries[1], Entry::Group(g) if g.name == "Production"));
+    }
+
+    #[test]
+    fn test_load_yaml() {
+        let content = load_from_str_as(
+            "
+# Comments are fine here
+editor: vim
+actions:
+  - name: Deploy
+    cmd: deploy.sh
+  - Production:
+      - name: Server
+        cmd: ssh server
+",
+            Format::Yaml,
+        )
+        .unwrap();
+
+        assert_eq!(content.editor.as_deref(), Some("vim"));
+        let entries = content.actions.unwrap();
+        assert!(matches!(&entries[0], Entry::Action(a) if a.cmd == "deploy.sh"));
+        assert!(matches!(&entries[1], Entry::Group(g) if g.entries.len() == 1));
+    }
+
+    #[test]
+    fn test_other_formats_use_schema() {
+        let result = load_from_str_as(
+            "actions:
+  - name: No cmd
+",
+            Format::Yaml,
+        );
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+
+        let result = load_from_str_as("unknown = true", Format::Toml);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
+    }
+
     // Config loading tests
     #[test]
     fn test_load_from_str_empty() {
@@ -191,12 +1307,29 @@ mod tests {
         assert!(content.terminal.is_none());
         assert!(content.editor.is_none());
         assert!(content.actions.is_none());
+        assert!(content.include.is_empty());
     }
 
     #[test]
     fn test_load_from_str_with_terminal() {
         let content = load_from_str(r#"{"terminal": "kitty"}"#).unwrap();
-        assert_eq!(content.terminal.as_deref(), Some("kitty"));
+        assert_eq!(content.terminal, Some(vec!["kitty".to_string()]));
+    }
+
+    #[test]
+    fn test_load_from_str_with_terminal_chain() {
+        let content = load_from_str(r#"{"terminal": ["ghostty", "kitty", "xterm"]}"#).unwrap();
+        assert_eq!(
+            content.terminal,
+            Some(vec![
+                "ghostty".to_string(),
+                "kitty".to_string(),
+                "xterm".to_string()
+            ])
+        );
+
+        let result = load_from_str(r#"{"terminal": []}"#);
+        assert!(matches!(result, Err(SettingsError::ConfigValidation(_))));
     }
 
     #[test]
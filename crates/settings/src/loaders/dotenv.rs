@@ -0,0 +1,158 @@
+//! `.env` file parsing.
+//!
+//! Supports the common subset of the format:
+//!
+//! - `KEY=value` lines, optionally prefixed with `export`
+//! - blank lines and `#` comments
+//! - single-quoted values, taken literally
+//! - double-quoted values, with `\n`, `\t`, `\"` and `\\` escapes
+//! - unquoted values, with trailing ` # comments` removed
+//!
+//! Variable references in values are not expanded.
+
+use crate::error::SettingsError;
+use std::fs;
+use std::path::Path;
+
+/// Reads the variables from a `.env` file.
+///
+/// # Errors
+///
+/// Returns [`SettingsError::EnvFile`] if the file can't be read or a line
+/// is malformed.
+pub(crate) fn load(path: &Path) -> Result<Vec<(String, String)>, SettingsError> {
+    let env_file_error = |message: String| SettingsError::EnvFile {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let contents = fs::read_to_string(path).map_err(|e| env_file_error(e.to_string()))?;
+    parse(&contents).map_err(env_file_error)
+}
+
+/// Parses the contents of a `.env` file.
+fn parse(s: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+
+    for (index, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected KEY=value", index + 1))?;
+
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(format!("line {}: invalid variable name '{key}'", index + 1));
+        }
+
+        let value = parse_value(value.trim())
+            .ok_or_else(|| format!("line {}: unterminated quoted value", index + 1))?;
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses a value, returning `None` if a quote isn't closed.
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest.find('\'').map(|end| rest[..end].to_string());
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(out),
+                '\\' => match chars.next()? {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    other => out.push(other),
+                },
+                c => out.push(c),
+            }
+        }
+        return None;
+    }
+
+    let value = value.find(" #").map_or(value, |pos| &value[..pos]);
+    Some(value.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(s: &str) -> Vec<(String, String)> {
+        parse(s).unwrap()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_plain_values() {
+        assert_eq!(
+            vars("KUBECONFIG=/kube/prod\nAWS_PROFILE = prod\n"),
+            vec![
+                pair("KUBECONFIG", "/kube/prod"),
+                pair("AWS_PROFILE", "prod")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_export_and_blank_lines() {
+        assert_eq!(
+            vars("# Production\n\nexport REGION=eu-west-1 # Ireland\nEMPTY=\n"),
+            vec![pair("REGION", "eu-west-1"), pair("EMPTY", "")]
+        );
+    }
+
+    #[test]
+    fn test_quoted_values() {
+        assert_eq!(
+            vars(
+                r#"A='literal $HOME # not a comment'
+B="line\nbreak \"quoted\""
+C="a#b""#
+            ),
+            vec![
+                pair("A", "literal $HOME # not a comment"),
+                pair("B", "line\nbreak \"quoted\""),
+                pair("C", "a#b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_name_line() {
+        assert_eq!(
+            parse("A=1\nnot a pair").unwrap_err(),
+            "line 2: expected KEY=value"
+        );
+        assert_eq!(
+            parse("1A=x").unwrap_err(),
+            "line 1: invalid variable name '1A'"
+        );
+        assert_eq!(
+            parse("A=\"open").unwrap_err(),
+            "line 1: unterminated quoted value"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Config file formats.
+//!
+//! Every format is parsed into a [`serde_json::Value`] first, so that all
+//! formats share the same schema validation and deserialization into
+//! [`Entry`](crate::types::Entry) and [`Group`](crate::types::Group).
+
+use crate::error::SettingsError;
+use crate::loaders::jsonc;
+use serde_json::Value;
+use std::path::Path;
+
+/// A supported config file format.
+///
+/// JSON documents may contain comments and trailing commas (JSONC).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// File extensions recognized as config files, in lookup order.
+    pub const EXTENSIONS: &'static [&'static str] = &["json", "toml", "yaml", "yml"];
+
+    /// Determines the format from a file extension, defaulting to JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+
+    /// Returns `true` if the path has a recognized config file extension.
+    pub fn is_config_file(path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| Self::EXTENSIONS.contains(&ext))
+    }
+
+    /// Parses a document into a JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a format-specific parse error, which carries the line and
+    /// column of the problem in the original document.
+    pub fn parse(self, s: &str) -> Result<Value, SettingsError> {
+        match self {
+            Self::Json => {
+                serde_json::from_str(&jsonc::strip(s)).map_err(SettingsError::ConfigParse)
+            }
+            Self::Toml => toml::from_str(s).map_err(SettingsError::TomlParse),
+            Self::Yaml => serde_yaml_ng::from_str(s).map_err(SettingsError::YamlParse),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Format::from_path(Path::new("a.json")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("a.toml")), Format::Toml);
+        assert_eq!(Format::from_path(Path::new("a.yaml")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new("a.yml")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new(".xshuttle")), Format::Json);
+    }
+
+    #[test]
+    fn test_is_config_file() {
+        assert!(Format::is_config_file(Path::new("team.toml")));
+        assert!(!Format::is_config_file(Path::new("README.md")));
+    }
+
+    #[test]
+    fn test_formats_parse_to_same_value() {
+        let json =
+            r#"{"terminal": "kitty", "actions": [{"Prod": [{"name": "Web", "cmd": "ssh web"}]}]}"#;
+        let toml = r#"
+            terminal = "kitty"
+
+            [[actions]]
+            Prod = [{ name = "Web", cmd = "ssh web" }]
+        "#;
+        let yaml = "
+            terminal: kitty
+            actions:
+              - Prod:
+                  - name: Web
+                    cmd: ssh web
+        ";
+
+        let expected = Format::Json.parse(json).unwrap();
+        assert_eq!(Format::Toml.parse(toml).unwrap(), expected);
+        assert_eq!(Format::Yaml.parse(yaml).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_toml_error_has_location() {
+        let err = Format::Toml.parse("terminal = \n").unwrap_err();
+        assert!(matches!(err, SettingsError::TomlParse(_)));
+        assert!(err.to_string().contains("line 1"), "{err}");
+    }
+
+    #[test]
+    fn test_yaml_error_has_location() {
+        let err = Format::Yaml
+            .parse("actions:\n  - name: [unclosed\n")
+            .unwrap_err();
+        assert!(matches!(err, SettingsError::YamlParse(_)));
+        assert!(err.to_string().contains("line"), "{err}");
+    }
+}
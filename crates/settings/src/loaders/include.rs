@@ -0,0 +1,601 @@
+//! Config includes and fragment directories.
+//!
+//! A config file may pull in other config files through its `include` key,
+//! and the main config file is additionally merged with every JSON, TOML or
+//! YAML fragment in its fragment directory (see [`fragments_dir()`]). Actions
+//! from included files are appended in a deterministic order.
+//!
+//! A lenient [`Loader`] records problems as [`Diagnostic`]s and carries on
+//! with the files and entries that are valid.
+
+use crate::error::{Diagnostic, SettingsError};
+use crate::expand::expand_home;
+use crate::loaders::config::{ConfigContent, load_from_str_as, load_from_str_lenient};
+use crate::loaders::format::Format;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns the fragment directory belonging to a config file.
+///
+/// This is `conf.d` next to an XDG-style `config.json`, and the config path
+/// with a `.d` extension otherwise (`~/.xshuttle.json` → `~/.xshuttle.d`).
+pub(crate) fn fragments_dir(config: &Path) -> PathBuf {
+    if config.file_stem().is_some_and(|stem| stem == "config") {
+        config.with_file_name("conf.d")
+    } else {
+        config.with_extension("d")
+    }
+}
+
+/// Loads config files while following includes and detecting cycles.
+#[derive(Debug, Default)]
+pub(crate) struct Loader {
+    /// Canonical paths of the files currently being loaded.
+    stack: Vec<PathBuf>,
+    /// Canonical paths of all files loaded so far.
+    loaded: Vec<PathBuf>,
+    /// Whether to skip invalid files and entries instead of failing.
+    lenient: bool,
+    /// Problems recorded in lenient mode.
+    diagnostics: Vec<Diagnostic>,
+    /// Canonical paths of files that globs and fragment directories skip.
+    excluded: Vec<PathBuf>,
+}
+
+impl Loader {
+    /// Creates a loader that records problems instead of failing on them.
+    ///
+    /// Unreadable or unparseable files are skipped, as are entries and keys
+    /// that fail validation. See [`Loader::take_diagnostics()`].
+    pub(crate) fn lenient() -> Self {
+        Self {
+            lenient: true,
+            ..Self::default()
+        }
+    }
+
+    /// Loads the main config file, its includes and its fragment directory.
+    ///
+    /// A missing main file counts as empty, so fragments still load.
+    pub(crate) fn load_main(&mut self, path: &Path) -> Result<ConfigContent, SettingsError> {
+        let loaded = if path.exists() {
+            self.load(path)
+        } else {
+            Ok(ConfigContent::default())
+        };
+        let mut content = match loaded {
+            Ok(content) => content,
+            Err(e) => {
+                self.recover(path, e)?;
+                ConfigContent::default()
+            }
+        };
+
+        let fragments = match fragment_files(&fragments_dir(path)) {
+            Ok(fragments) => fragments,
+            Err(e) => {
+                self.recover(path, e)?;
+                Vec::new()
+            }
+        };
+        for fragment in fragments {
+            if self.is_excluded(&fragment) {
+                continue;
+            }
+            if let Some(fragment_content) = self.load_included(&fragment)? {
+                content.absorb(fragment_content);
+            }
+        }
+
+        Ok(content)
+    }
+
+    /// Keeps globs and fragment directories from loading `path`, for files
+    /// that are applied on their own, such as the local override.
+    pub(crate) fn exclude(&mut self, path: &Path) {
+        if let Ok(canonical) = fs::canonicalize(path) {
+            self.excluded.push(canonical);
+        }
+    }
+
+    /// Returns `true` if `path` was excluded with [`Loader::exclude()`].
+    fn is_excluded(&self, path: &Path) -> bool {
+        fs::canonicalize(path).is_ok_and(|canonical| self.excluded.contains(&canonical))
+    }
+
+    /// Takes the canonical paths of all files loaded so far.
+    pub(crate) fn take_sources(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.loaded)
+    }
+
+    /// Takes the problems recorded in lenient mode.
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Records an error in lenient mode, or returns it otherwise.
+    fn recover(&mut self, file: &Path, error: SettingsError) -> Result<(), SettingsError> {
+        if !self.lenient {
+            return Err(error);
+        }
+        self.diagnostics
+            .push(Diagnostic::from_error(Some(file), &error));
+        Ok(())
+    }
+
+    /// Loads a single config file and, recursively, its includes.
+    pub(crate) fn load(&mut self, path: &Path) -> Result<ConfigContent, SettingsError> {
+        let canonical = fs::canonicalize(path)?;
+
+        if let Some(start) = self.stack.iter().position(|p| *p == canonical) {
+            let mut cycle = self.stack[start..].to_vec();
+            cycle.push(canonical);
+            return Err(SettingsError::IncludeCycle(cycle));
+        }
+
+        self.stack.push(canonical.clone());
+        self.loaded.push(canonical);
+        let result = self.load_file(path);
+        self.stack.pop();
+
+        result
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<ConfigContent, SettingsError> {
+        let contents = fs::read_to_string(path)?;
+        let format = Format::from_path(path);
+        let mut content = if self.lenient {
+            let (content, skipped) = load_from_str_lenient(&contents, format)?;
+            self.diagnostics.extend(
+                skipped
+                    .into_iter()
+                    .map(|error| Diagnostic::skipped(path, error)),
+            );
+            content
+        } else {
+            load_from_str_as(&contents, format)?
+        };
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        for pattern in std::mem::take(&mut content.include) {
+            let files = match resolve_pattern(base, &pattern) {
+                Ok(files) => files,
+                Err(e) => {
+                    self.recover(path, e)?;
+                    continue;
+                }
+            };
+            for file in files {
+                if is_glob(&pattern) && self.is_excluded(&file) {
+                    continue;
+                }
+                if let Some(included) = self.load_included(&file)? {
+                    content.absorb(included);
+                }
+            }
+        }
+
+        Ok(content)
+    }
+
+    /// Loads an included file, naming it in any error.
+    ///
+    /// Returns `None` if the file was already loaded through another include.
+    pub(crate) fn load_included(
+        &mut self,
+        path: &Path,
+    ) -> Result<Option<ConfigContent>, SettingsError> {
+        let in_fragment = |e| SettingsError::Fragment {
+            path: path.to_path_buf(),
+            source: Box::new(e),
+        };
+
+        let canonical = match fs::canonicalize(path) {
+            Ok(canonical) => canonical,
+            Err(e) => return self.recover(path, in_fragment(e.into())).map(|()| None),
+        };
+        if self.loaded.contains(&canonical) && !self.stack.contains(&canonical) {
+            return Ok(None);
+        }
+
+        match self.load(path) {
+            Ok(content) => Ok(Some(content)),
+            // Cycles already name every file involved
+            Err(e @ SettingsError::IncludeCycle(_)) => self.recover(path, e).map(|()| None),
+            Err(e) => self.recover(path, in_fragment(e)).map(|()| None),
+        }
+    }
+}
+
+/// Resolves an include pattern to a sorted list of files.
+///
+/// Relative patterns are resolved against `base`, the directory of the
+/// including file. Globs may match nothing; plain paths must exist.
+fn resolve_pattern(base: &Path, pattern: &str) -> Result<Vec<PathBuf>, SettingsError> {
+    let path = base.join(expand_home(pattern));
+
+    if !is_glob(pattern) {
+        if !path.exists() {
+            return Err(SettingsError::Fragment {
+                path,
+                source: Box::new(SettingsError::ConfigIo(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "included file not found",
+                ))),
+            });
+        }
+        return Ok(vec![path]);
+    }
+
+    let invalid = |message: String| SettingsError::IncludePattern {
+        pattern: pattern.to_string(),
+        message,
+    };
+
+    let full_pattern = path
+        .to_str()
+        .ok_or_else(|| invalid("path is not valid UTF-8".to_string()))?;
+
+    let mut files = glob::glob(full_pattern)
+        .map_err(|e| invalid(e.to_string()))?
+        .filter_map(Result::ok)
+        .filter(|p| p.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Returns `true` if an include pattern is a glob rather than a path.
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Lists the config files in a fragment directory, sorted by file name.
+///
+/// Returns an empty list if the directory doesn't exist.
+fn fragment_files(dir: &Path) -> Result<Vec<PathBuf>, SettingsError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file() && Format::is_config_file(p))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Severity;
+    use crate::types::Entry;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("xshuttle-include-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn action_names(content: &ConfigContent) -> Vec<&str> {
+        content
+            .actions
+            .iter()
+            .flatten()
+            .map(|e| match e {
+                Entry::Action(a) => a.name.as_str(),
+                Entry::Group(g) => g.name.as_str(),
+            })
+            .collect()
+    }
+
+    fn action(name: &str) -> String {
+        format!(r#"{{"name": "{name}", "cmd": "echo {name}"}}"#)
+    }
+
+    #[test]
+    fn test_fragments_dir() {
+        assert_eq!(
+            fragments_dir(Path::new("/home/u/.config/xshuttle/config.json")),
+            PathBuf::from("/home/u/.config/xshuttle/conf.d")
+        );
+        assert_eq!(
+            fragments_dir(Path::new("/home/u/.xshuttle.json")),
+            PathBuf::from("/home/u/.xshuttle.d")
+        );
+    }
+
+    #[test]
+    fn test_include_single_file() {
+        let dir = temp_dir("single");
+        fs::write(
+            dir.join("config.json"),
+            format!(
+                r#"{{"include": "team.json", "actions": [{}]}}"#,
+                action("Own")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("team.json"),
+            format!(r#"{{"actions": [{}]}}"#, action("Shared")),
+        )
+        .unwrap();
+
+        let mut loader = Loader::default();
+        let content = loader.load_main(&dir.join("config.json")).unwrap();
+        assert_eq!(action_names(&content), vec!["Own", "Shared"]);
+        assert_eq!(loader.take_sources().len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_glob_sorted() {
+        let dir = temp_dir("glob");
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::write(dir.join("config.json"), r#"{"include": ["shared/*.json"]}"#).unwrap();
+        for name in ["b", "a", "c"] {
+            fs::write(
+                dir.join("shared").join(format!("{name}.json")),
+                format!(r#"{{"actions": [{}]}}"#, action(name)),
+            )
+            .unwrap();
+        }
+
+        let content = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap();
+        assert_eq!(action_names(&content), vec!["a", "b", "c"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_glob_without_matches() {
+        let dir = temp_dir("glob-empty");
+        fs::write(dir.join("config.json"), r#"{"include": "missing/*.json"}"#).unwrap();
+
+        let content = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap();
+        assert!(action_names(&content).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_missing_file_named() {
+        let dir = temp_dir("missing");
+        fs::write(dir.join("config.json"), r#"{"include": "nope.json"}"#).unwrap();
+
+        let err = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap_err();
+        assert!(
+            matches!(err, SettingsError::Fragment { ref path, .. } if path.ends_with("nope.json"))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fragment_dir_merged_in_order() {
+        let dir = temp_dir("confd");
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(
+            dir.join("config.json"),
+            format!(r#"{{"actions": [{}]}}"#, action("Main")),
+        )
+        .unwrap();
+        for name in ["20-private", "10-team"] {
+            fs::write(
+                dir.join("conf.d").join(format!("{name}.json")),
+                format!(r#"{{"actions": [{}]}}"#, action(name)),
+            )
+            .unwrap();
+        }
+        // Non-JSON files are ignored
+        fs::write(dir.join("conf.d").join("README.md"), "notes").unwrap();
+
+        let content = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap();
+        assert_eq!(
+            action_names(&content),
+            vec!["Main", "10-team", "20-private"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fragment_error_names_fragment() {
+        let dir = temp_dir("confd-error");
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(dir.join("config.json"), "{}").unwrap();
+        fs::write(
+            dir.join("conf.d").join("broken.json"),
+            r#"{"actions": [{"name": "No cmd"}]}"#,
+        )
+        .unwrap();
+
+        let err = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap_err();
+        match &err {
+            SettingsError::Fragment { path, source } => {
+                assert!(path.ends_with("broken.json"));
+                assert!(matches!(**source, SettingsError::ConfigValidation(_)));
+            }
+            e => panic!("Expected fragment error, got {e}"),
+        }
+        assert!(err.to_string().contains("broken.json"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_cycle_detected() {
+        let dir = temp_dir("cycle");
+        fs::write(dir.join("config.json"), r#"{"include": "a.json"}"#).unwrap();
+        fs::write(dir.join("a.json"), r#"{"include": "b.json"}"#).unwrap();
+        fs::write(dir.join("b.json"), r#"{"include": "a.json"}"#).unwrap();
+
+        let err = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap_err();
+        match err {
+            SettingsError::IncludeCycle(cycle) => {
+                assert_eq!(cycle.len(), 3);
+                assert!(cycle[0].ends_with("a.json"));
+                assert!(cycle[1].ends_with("b.json"));
+                assert!(cycle[2].ends_with("a.json"));
+            }
+            e => panic!("Expected include cycle, got {e}"),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_self_include_is_cycle() {
+        let dir = temp_dir("self");
+        fs::write(dir.join("config.json"), r#"{"include": "*.json"}"#).unwrap();
+
+        let err = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap_err();
+        assert!(matches!(err, SettingsError::IncludeCycle(_)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_diamond_include_loaded_once() {
+        let dir = temp_dir("diamond");
+        fs::write(
+            dir.join("config.json"),
+            r#"{"include": ["a.json", "b.json"]}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("a.json"), r#"{"include": "common.json"}"#).unwrap();
+        fs::write(dir.join("b.json"), r#"{"include": "common.json"}"#).unwrap();
+        fs::write(
+            dir.join("common.json"),
+            format!(r#"{{"actions": [{}]}}"#, action("Common")),
+        )
+        .unwrap();
+
+        let content = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap();
+        assert_eq!(action_names(&content), vec!["Common"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_main_scalars_win_over_includes() {
+        let dir = temp_dir("scalars");
+        fs::write(
+            dir.join("config.json"),
+            r#"{"terminal": "kitty", "include": "team.json"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("team.json"),
+            r#"{"terminal": "xterm", "editor": "vim"}"#,
+        )
+        .unwrap();
+
+        let content = Loader::default()
+            .load_main(&dir.join("config.json"))
+            .unwrap();
+        assert_eq!(content.terminal, Some(vec!["kitty".to_string()]));
+        assert_eq!(content.editor.as_deref(), Some("vim"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_lenient_skips_broken_files() {
+        let dir = temp_dir("lenient");
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(
+            dir.join("config.json"),
+            format!(
+                r#"{{"include": ["nope.json", "team.json"], "actions": [{}]}}"#,
+                action("Main")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("team.json"),
+            format!(
+                r#"{{"actions": [{}, {{"name": "No cmd"}}]}}"#,
+                action("Team")
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("conf.d").join("broken.json"), "{").unwrap();
+
+        let mut loader = Loader::lenient();
+        let content = loader.load_main(&dir.join("config.json")).unwrap();
+        assert_eq!(action_names(&content), vec!["Main", "Team"]);
+
+        let diagnostics = loader.take_diagnostics();
+        let files: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.file.as_ref().unwrap().file_name().unwrap()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (Severity::Error, "nope.json".as_ref()),
+                (Severity::Warning, "team.json".as_ref()),
+                (Severity::Error, "broken.json".as_ref()),
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_lenient_breaks_cycle() {
+        let dir = temp_dir("lenient-cycle");
+        fs::write(
+            dir.join("config.json"),
+            format!(
+                r#"{{"include": "a.json", "actions": [{}]}}"#,
+                action("Main")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a.json"),
+            format!(
+                r#"{{"include": "config.json", "actions": [{}]}}"#,
+                action("A")
+            ),
+        )
+        .unwrap();
+
+        let mut loader = Loader::lenient();
+        let content = loader.load_main(&dir.join("config.json")).unwrap();
+        assert_eq!(action_names(&content), vec!["Main", "A"]);
+
+        let diagnostics = loader.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.starts_with("include cycle"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
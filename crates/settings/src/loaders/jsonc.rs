@@ -0,0 +1,144 @@
+//! JSON with comments (JSONC) support.
+//!
+//! [`strip()`] turns JSONC into plain JSON by blanking out `//` and `/* */`
+//! comments and trailing commas. Every removed byte is replaced by a space
+//! (newlines are kept), so line and column numbers reported for the
+//! stripped document are still correct for the original.
+
+/// Strips comments and trailing commas from a JSONC document.
+pub(crate) fn strip(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = bytes.to_vec();
+    // Position of a comma that is trailing if the next token closes a container
+    let mut pending_comma: Option<usize> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                pending_comma = None;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    out[i] = b' ';
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = s[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |pos| i + 2 + pos + 2);
+                blank(&mut out[i..end]);
+                i = end;
+            }
+            b',' => {
+                pending_comma = Some(i);
+                i += 1;
+            }
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    out[comma] = b' ';
+                }
+                i += 1;
+            }
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            _ => {
+                pending_comma = None;
+                i += 1;
+            }
+        }
+    }
+
+    // Only ASCII bytes outside of strings were replaced, so this stays UTF-8
+    String::from_utf8(out).expect("stripping JSONC keeps valid UTF-8")
+}
+
+/// Replaces every byte except newlines with a space.
+fn blank(bytes: &mut [u8]) {
+    for b in bytes.iter_mut().filter(|b| **b != b'\n') {
+        *b = b' ';
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn parse(s: &str) -> Value {
+        serde_json::from_str(&strip(s)).unwrap()
+    }
+
+    #[test]
+    fn test_plain_json_unchanged() {
+        let s = r#"{"a": [1, 2], "b": "c"}"#;
+        assert_eq!(strip(s), s);
+    }
+
+    #[test]
+    fn test_line_comments() {
+        let value = parse(
+            r#"{
+                // The terminal to use
+                "terminal": "kitty" // trailing note
+            }"#,
+        );
+        assert_eq!(value, json!({"terminal": "kitty"}));
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let value = parse(
+            r#"{ /* multi
+            line */ "editor": /* inline */ "vim" }"#,
+        );
+        assert_eq!(value, json!({"editor": "vim"}));
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let value = parse(
+            r#"{
+                "actions": [
+                    {"name": "A", "cmd": "a",},
+                    {"name": "B", "cmd": "b"}, // last
+                ],
+            }"#,
+        );
+        assert_eq!(
+            value,
+            json!({"actions": [{"name": "A", "cmd": "a"}, {"name": "B", "cmd": "b"}]})
+        );
+    }
+
+    #[test]
+    fn test_comment_markers_inside_strings_kept() {
+        let value = parse(r#"{"cmd": "curl http://example.com/* ,]", "x": "\"//\""}"#);
+        assert_eq!(
+            value,
+            json!({"cmd": "curl http://example.com/* ,]", "x": "\"//\""})
+        );
+    }
+
+    #[test]
+    fn test_positions_preserved() {
+        let s = "{\n  // über comment\n  \"a\": x\n}";
+        let stripped = strip(s);
+        assert_eq!(stripped.len(), s.len());
+        assert_eq!(stripped.lines().count(), s.lines().count());
+
+        let err = serde_json::from_str::<Value>(&stripped).unwrap_err();
+        assert_eq!(err.line(), 3);
+        assert_eq!(err.column(), 8);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        assert!(serde_json::from_str::<Value>(&strip("{} /* open")).is_ok());
+    }
+}
@@ -0,0 +1,510 @@
+//! Mapping JSON pointers back to positions in the source text.
+//!
+//! Schema validation runs on a parsed [`serde_json::Value`] and reports
+//! errors by JSON pointer. A [`Source`] keeps the original document to find
+//! where the value at a pointer is written: JSON is scanned directly (with
+//! comments already blanked out, see [`jsonc`](super::jsonc)), TOML is
+//! parsed again with spans, and YAML is walked with a deserializer that
+//! stops at the pointer.
+
+use crate::error::Location;
+use crate::loaders::format::Format;
+use crate::loaders::jsonc;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use toml::de::{DeTable, DeValue};
+
+/// A config document whose errors can be located.
+pub(crate) enum Source<'a> {
+    /// JSON, with comments blanked out, and the original text.
+    Json {
+        text: String,
+        original: &'a str,
+    },
+    Toml(&'a str),
+    Yaml(&'a str),
+}
+
+impl<'a> Source<'a> {
+    pub(crate) fn new(format: Format, original: &'a str) -> Self {
+        match format {
+            Format::Json => Self::Json {
+                text: jsonc::strip(original),
+                original,
+            },
+            Format::Toml => Self::Toml(original),
+            Format::Yaml => Self::Yaml(original),
+        }
+    }
+
+    /// Finds the location of the value at a JSON pointer.
+    ///
+    /// Object members are located at their key, array elements at the
+    /// element. Returns `None` if the pointer doesn't resolve.
+    pub(crate) fn locate(&self, pointer: &str) -> Option<Location> {
+        match self {
+            Self::Json { text, original } => locate(text, original, pointer),
+            Self::Toml(original) => locate_toml(original, pointer),
+            Self::Yaml(original) => locate_yaml(original, pointer),
+        }
+    }
+}
+
+/// Splits a JSON pointer into unescaped segments.
+fn segments(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let segments = pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    Some(segments)
+}
+
+/// Finds the source location of the value at a JSON pointer.
+///
+/// `text` is the document to scan and must be plain JSON; `source` is the
+/// original document with identical byte offsets, used for column counting.
+/// Object members are located at their key, array elements at the element.
+///
+/// Returns `None` if the pointer doesn't resolve.
+pub(crate) fn locate(text: &str, source: &str, pointer: &str) -> Option<Location> {
+    let mut scanner = Scanner {
+        bytes: text.as_bytes(),
+        pos: 0,
+    };
+    scanner.skip_ws();
+    let mut anchor = scanner.pos;
+
+    for segment in segments(pointer)? {
+        anchor = scanner.enter(&segment)?;
+    }
+
+    Some(Location::from_offset(source, anchor))
+}
+
+/// Finds the location of the value at a JSON pointer in a TOML document.
+fn locate_toml(source: &str, pointer: &str) -> Option<Location> {
+    let root = DeTable::parse(source).ok()?;
+    let segments = segments(pointer)?;
+
+    let offset = if segments.is_empty() {
+        root.span().start
+    } else {
+        toml_offset(&DeValue::Table(root.into_inner()), &segments)?
+    };
+    Some(Location::from_offset(source, offset))
+}
+
+/// Returns the byte offset of the key or element the segments lead to.
+fn toml_offset(value: &DeValue, segments: &[String]) -> Option<usize> {
+    let (segment, rest) = segments.split_first()?;
+    let (anchor, child) = match value {
+        DeValue::Table(table) => {
+            let (key, child) = table.iter().find(|(key, _)| key.get_ref() == segment)?;
+            (key.span().start, child)
+        }
+        DeValue::Array(array) => {
+            let child = array.get(segment.parse::<usize>().ok()?)?;
+            (child.span().start, child)
+        }
+        _ => return None,
+    };
+
+    if rest.is_empty() {
+        Some(anchor)
+    } else {
+        toml_offset(child.get_ref(), rest)
+    }
+}
+
+/// Marks the error [`Seek`] raises once it reaches the pointer.
+const FOUND: &str = "found the value at the pointer";
+
+/// Finds the location of the value at a JSON pointer in a YAML document.
+///
+/// YAML values don't keep their positions, but the deserializer attaches
+/// the position of the current node to errors. Walking the document and
+/// failing at the pointer therefore reveals where it is.
+fn locate_yaml(source: &str, pointer: &str) -> Option<Location> {
+    let segments = segments(pointer)?;
+    let error = Seek {
+        segments: &segments,
+    }
+    .deserialize(serde_yaml_ng::Deserializer::from_str(source))
+    .err()?;
+
+    if !error.to_string().contains(FOUND) {
+        return None;
+    }
+    let location = error.location()?;
+    Some(Location {
+        line: location.line(),
+        column: location.column(),
+    })
+}
+
+/// Walks a document down the given pointer segments.
+struct Seek<'a> {
+    segments: &'a [String],
+}
+
+impl Seek<'_> {
+    /// Fails with [`FOUND`] if this is the value at the pointer.
+    fn scalar<E: de::Error>(&self) -> Result<(), E> {
+        if self.segments.is_empty() {
+            Err(E::custom(FOUND))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Seek<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Seek<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let Some((segment, rest)) = self.segments.split_first() else {
+            return Err(de::Error::custom(FOUND));
+        };
+        while let Some(found) = map.next_key_seed(Key {
+            segment,
+            here: rest.is_empty(),
+        })? {
+            if found {
+                return map.next_value_seed(Seek { segments: rest });
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let Some((segment, rest)) = self.segments.split_first() else {
+            return Err(de::Error::custom(FOUND));
+        };
+        let Ok(index) = segment.parse::<usize>() else {
+            return Ok(());
+        };
+        for _ in 0..index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+        }
+        seq.next_element_seed(Seek { segments: rest }).map(|_| ())
+    }
+}
+
+/// Checks whether a mapping key is the one a pointer segment names, failing
+/// with [`FOUND`] at the key if it's the last segment.
+struct Key<'a> {
+    segment: &'a str,
+    here: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for Key<'_> {
+    type Value = bool;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl Visitor<'_> for Key<'_> {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a mapping key")
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<bool, E> {
+        match key == self.segment {
+            true if self.here => Err(E::custom(FOUND)),
+            found => Ok(found),
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, key: bool) -> Result<bool, E> {
+        self.visit_str(&key.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, key: i64) -> Result<bool, E> {
+        self.visit_str(&key.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, key: u64) -> Result<bool, E> {
+        self.visit_str(&key.to_string())
+    }
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_ws();
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// Moves to the value of the given member or element of the current
+    /// container, returning the anchor position to report.
+    fn enter(&mut self, segment: &str) -> Option<usize> {
+        self.skip_ws();
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_ws();
+                    let key_pos = self.pos;
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    self.skip_ws();
+                    if key == segment {
+                        return Some(key_pos);
+                    }
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let index: usize = segment.parse().ok()?;
+                for _ in 0..index {
+                    self.skip_ws();
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+                self.skip_ws();
+                Some(self.pos)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads a string starting at the current position, unescaping only
+    /// what's needed to compare keys.
+    fn string(&mut self) -> Option<String> {
+        if self.peek()? != b'"' {
+            return None;
+        }
+        let start = self.pos + 1;
+        self.skip_string()?;
+        let raw = std::str::from_utf8(&self.bytes[start..self.pos - 1]).ok()?;
+        Some(serde_json::from_str(&format!("\"{raw}\"")).unwrap_or_else(|_| raw.to_string()))
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => self.skip_string(),
+            b'{' | b'[' => {
+                let mut depth = 0usize;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.skip_string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                return Some(());
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                Some(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str, pointer: &str) -> Option<(usize, usize)> {
+        locate(text, text, pointer).map(|l| (l.line, l.column))
+    }
+
+    const DOC: &str = r#"{
+  "terminal": "kitty",
+  "actions": [
+    {"name": "Top", "cmd": "echo {\"x\": [1]}"},
+    {"Prod": [
+      {"name": "Web", "cmd": 1}
+    ]}
+  ]
+}"#;
+
+    #[test]
+    fn test_locate_root() {
+        assert_eq!(at(DOC, ""), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_locate_member_key() {
+        assert_eq!(at(DOC, "/terminal"), Some((2, 3)));
+        assert_eq!(at(DOC, "/actions"), Some((3, 3)));
+    }
+
+    #[test]
+    fn test_locate_array_elements() {
+        assert_eq!(at(DOC, "/actions/0"), Some((4, 5)));
+        assert_eq!(at(DOC, "/actions/1"), Some((5, 5)));
+    }
+
+    #[test]
+    fn test_locate_nested() {
+        assert_eq!(at(DOC, "/actions/1/Prod/0"), Some((6, 7)));
+        assert_eq!(at(DOC, "/actions/1/Prod/0/cmd"), Some((6, 23)));
+    }
+
+    #[test]
+    fn test_locate_missing() {
+        assert_eq!(at(DOC, "/nope"), None);
+        assert_eq!(at(DOC, "/actions/5"), None);
+        assert_eq!(at(DOC, "/terminal/0"), None);
+    }
+
+    #[test]
+    fn test_locate_escaped_pointer() {
+        assert_eq!(at(r#"{"a/b": {"c~d": 1}}"#, "/a~1b/c~0d"), Some((1, 10)));
+    }
+
+    const TOML: &str = r#"terminal = "kitty"
+
+[[actions]]
+name = "Top"
+cmd = "htop"
+
+[[actions]]
+Prod = [
+  { name = "Web", cmd = 1 },
+]
+"#;
+
+    fn at_toml(pointer: &str) -> Option<(usize, usize)> {
+        Source::new(Format::Toml, TOML)
+            .locate(pointer)
+            .map(|l| (l.line, l.column))
+    }
+
+    #[test]
+    fn test_locate_toml() {
+        assert_eq!(at_toml("/terminal"), Some((1, 1)));
+        assert_eq!(at_toml("/actions/0/cmd"), Some((5, 1)));
+        assert_eq!(at_toml("/actions/1/Prod"), Some((8, 1)));
+        assert_eq!(at_toml("/actions/1/Prod/0"), Some((9, 3)));
+        assert_eq!(at_toml("/actions/1/Prod/0/cmd"), Some((9, 19)));
+        assert_eq!(at_toml("/actions/2"), None);
+        assert_eq!(at_toml("/nope"), None);
+    }
+
+    const YAML: &str = "terminal: kitty
+actions:
+  - name: Top
+    cmd: htop
+  - Prod:
+      - {name: Web, cmd: 1}
+";
+
+    fn at_yaml(pointer: &str) -> Option<(usize, usize)> {
+        Source::new(Format::Yaml, YAML)
+            .locate(pointer)
+            .map(|l| (l.line, l.column))
+    }
+
+    #[test]
+    fn test_locate_yaml() {
+        assert_eq!(at_yaml("/terminal"), Some((1, 1)));
+        assert_eq!(at_yaml("/actions/0"), Some((3, 5)));
+        assert_eq!(at_yaml("/actions/0/cmd"), Some((4, 5)));
+        assert_eq!(at_yaml("/actions/1/Prod/0"), Some((6, 9)));
+        assert_eq!(at_yaml("/actions/1/Prod/0/cmd"), Some((6, 21)));
+        assert_eq!(at_yaml("/actions/2"), None);
+        assert_eq!(at_yaml("/terminal/0"), None);
+        assert_eq!(at_yaml("/nope"), None);
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        let doc = r#"{"ü": 1, "x": 2}"#;
+        assert_eq!(at(doc, "/x"), Some((1, 10)));
+    }
+}
@@ -1,2 +1,8 @@
 pub(crate) mod config;
+pub(crate) mod dotenv;
+pub(crate) mod format;
+pub(crate) mod include;
+pub(crate) mod jsonc;
+pub(crate) mod location;
+pub(crate) mod prune;
 pub(crate) mod ssh;
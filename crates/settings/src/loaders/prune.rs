@@ -0,0 +1,206 @@
+//! Dropping the invalid parts of a config document.
+//!
+//! Lenient loading validates a document, removes the smallest unit that
+//! contains each error with [`prune()`] and validates again, so that one
+//! broken action doesn't take the rest of the config down with it. The
+//! units are:
+//!
+//! - an entry anywhere in the `actions` tree, including all of a group's
+//!   entries if the group itself is malformed
+//! - a top-level key, for errors outside of `actions`
+//! - unknown top-level keys, for errors on the document itself
+
+use crate::error::ValidationError;
+use serde_json::{Map, Value};
+
+/// Removes the parts of `value` that contain the given errors.
+///
+/// `known_keys` are the allowed top-level keys. Returns `true` if anything
+/// was removed.
+pub(crate) fn prune(value: &mut Value, errors: &[ValidationError], known_keys: &[&str]) -> bool {
+    let mut changed = false;
+    for error in errors {
+        changed |= remove(value, &error.path, known_keys);
+    }
+
+    // Removed entries are left as `null` until now so indices stay valid
+    if let Some(actions) = value.get_mut("actions") {
+        sweep(actions);
+    }
+
+    changed
+}
+
+fn remove(value: &mut Value, pointer: &str, known_keys: &[&str]) -> bool {
+    let segments: Vec<String> = pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect();
+
+    let Some(root) = value.as_object_mut() else {
+        *value = Value::Object(Map::new());
+        return true;
+    };
+
+    match segments.as_slice() {
+        [] => {
+            let len = root.len();
+            root.retain(|key, _| known_keys.contains(&key.as_str()));
+            root.len() != len
+        }
+        [key, rest @ ..] if key == "actions" && !rest.is_empty() => root
+            .get_mut("actions")
+            .is_some_and(|entries| remove_entry(entries, rest)),
+        [key, ..] => root.remove(key).is_some(),
+    }
+}
+
+/// Replaces the entry the error is in with a `null` tombstone.
+///
+/// `segments` start with the entry's index in `entries`. Errors inside a
+/// group's entries only remove the nested entry.
+fn remove_entry(entries: &mut Value, segments: &[String]) -> bool {
+    let Some((index, rest)) = segments.split_first() else {
+        return false;
+    };
+    let Some(entry) = index.parse().ok().and_then(|i: usize| entries.get_mut(i)) else {
+        return false;
+    };
+
+    if let [key, nested @ ..] = rest
+        && !nested.is_empty()
+        && !is_action(entry)
+        && let Some(children) = entry.get_mut(key.as_str())
+        && children.is_array()
+    {
+        return remove_entry(children, nested);
+    }
+
+    if entry.is_null() {
+        return false;
+    }
+    *entry = Value::Null;
+    true
+}
+
+/// Drops tombstones from an entry list and the groups within it.
+fn sweep(entries: &mut Value) {
+    let Some(items) = entries.as_array_mut() else {
+        return;
+    };
+
+    items.retain(|entry| !entry.is_null());
+    for group in items.iter_mut().filter(|entry| !is_action(entry)) {
+        if let Some(map) = group.as_object_mut() {
+            map.values_mut().for_each(sweep);
+        }
+    }
+}
+
+/// Mirrors the schema: objects with a `name` or `cmd` key are actions.
+fn is_action(entry: &Value) -> bool {
+    entry
+        .as_object()
+        .is_some_and(|map| map.contains_key("name") || map.contains_key("cmd"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error(path: &str) -> ValidationError {
+        ValidationError {
+            path: path.to_string(),
+            message: String::new(),
+            location: None,
+            suggestion: None,
+        }
+    }
+
+    fn pruned(mut value: Value, paths: &[&str]) -> Value {
+        let errors: Vec<_> = paths.iter().map(|p| error(p)).collect();
+        prune(&mut value, &errors, &["terminal", "actions"]);
+        value
+    }
+
+    #[test]
+    fn test_removes_top_level_action() {
+        let value = json!({"actions": [
+            {"name": "A", "cmd": "a"},
+            {"name": "B", "cmd": 1},
+            {"name": "C", "cmd": "c"}
+        ]});
+        assert_eq!(
+            pruned(value, &["/actions/1/cmd"]),
+            json!({"actions": [{"name": "A", "cmd": "a"}, {"name": "C", "cmd": "c"}]})
+        );
+    }
+
+    #[test]
+    fn test_removes_only_nested_entry() {
+        let value = json!({"actions": [
+            {"Prod": [
+                {"name": "Web", "cmd": "ssh web"},
+                {"name": "Db"}
+            ]}
+        ]});
+        assert_eq!(
+            pruned(value, &["/actions/0/Prod/1"]),
+            json!({"actions": [{"Prod": [{"name": "Web", "cmd": "ssh web"}]}]})
+        );
+    }
+
+    #[test]
+    fn test_removes_malformed_group() {
+        let value = json!({"actions": [
+            {"Prod": "not a list"},
+            {"name": "A", "cmd": "a"}
+        ]});
+        assert_eq!(
+            pruned(value, &["/actions/0/Prod"]),
+            json!({"actions": [{"name": "A", "cmd": "a"}]})
+        );
+    }
+
+    #[test]
+    fn test_multiple_errors_keep_indices_stable() {
+        let value = json!({"actions": [
+            {"name": "A"},
+            {"name": "B", "cmd": "b"},
+            {"name": "C"},
+            {"name": "C", "cmd": "c", "extra": 1}
+        ]});
+        assert_eq!(
+            pruned(value, &["/actions/0", "/actions/2", "/actions/3"]),
+            json!({"actions": [{"name": "B", "cmd": "b"}]})
+        );
+    }
+
+    #[test]
+    fn test_removes_invalid_top_level_key() {
+        let value = json!({"terminal": 1, "actions": []});
+        assert_eq!(pruned(value, &["/terminal"]), json!({"actions": []}));
+
+        let value = json!({"actions": "nope", "terminal": "kitty"});
+        assert_eq!(pruned(value, &["/actions"]), json!({"terminal": "kitty"}));
+    }
+
+    #[test]
+    fn test_removes_unknown_top_level_keys() {
+        let value = json!({"terminal": "kitty", "termnial": "xterm"});
+        assert_eq!(pruned(value, &[""]), json!({"terminal": "kitty"}));
+    }
+
+    #[test]
+    fn test_non_object_document_replaced() {
+        assert_eq!(pruned(json!([1, 2]), &[""]), json!({}));
+    }
+
+    #[test]
+    fn test_nothing_to_remove() {
+        let mut value = json!({"actions": []});
+        assert!(!prune(&mut value, &[error("/actions/4")], &["actions"]));
+    }
+}
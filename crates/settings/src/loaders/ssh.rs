@@ -3,7 +3,7 @@ use ssh2_config::{ParseRule, SshConfig};
 use std::path::PathBuf;
 
 /// Returns the default SSH config file path (~/.ssh/config).
-fn ssh_config_path() -> Option<PathBuf> {
+pub(crate) fn ssh_config_path() -> Option<PathBuf> {
     dirs::home_dir().map(|home| home.join(".ssh").join("config"))
 }
 
@@ -4,7 +4,7 @@
 //! with assigned IDs while preserving tree structure for menu building.
 
 use crate::host::Host;
-use crate::types::{Action, Entry, Group};
+use crate::types::{Action, Defaults, Entry, Group};
 use std::fmt;
 
 /// Unique identifier for a leaf node within a [`Nodes`] container.
@@ -89,6 +89,16 @@ impl<T> Node<T> {
     pub fn is_group(&self) -> bool {
         matches!(self, Self::Group { .. })
     }
+
+    /// Returns the IDs of all leaves in this node, depth-first: its own ID
+    /// for a `Leaf`, and those of all nested leaves for a `Group`.
+    #[must_use]
+    pub fn leaf_ids(&self) -> Vec<NodeId> {
+        match self {
+            Self::Leaf { id, .. } => vec![*id],
+            Self::Group { children, .. } => children.iter().flat_map(Node::leaf_ids).collect(),
+        }
+    }
 }
 
 /// Generic container providing O(1) lookup by [`NodeId`].
@@ -109,19 +119,23 @@ pub struct Nodes<T> {
 
 impl Nodes<Action> {
     /// Build from config entries, assigning IDs during depth-first traversal.
+    ///
+    /// Actions inherit the defaults of the groups they are in.
     #[must_use]
     pub fn from_entries(entries: Vec<Entry>) -> Self {
         let mut leaves = Vec::new();
+        let defaults = Defaults::default();
         let tree = entries
             .into_iter()
-            .map(|e| Self::convert_entry(e, &mut leaves))
+            .map(|e| Self::convert_entry(e, &defaults, &mut leaves))
             .collect();
         Self { tree, leaves }
     }
 
-    fn convert_entry(entry: Entry, leaves: &mut Vec<Action>) -> Node<Action> {
+    fn convert_entry(entry: Entry, defaults: &Defaults, leaves: &mut Vec<Action>) -> Node<Action> {
         match entry {
-            Entry::Action(action) => {
+            Entry::Action(mut action) => {
+                action.inherit(defaults);
                 let id = NodeId::from_index(leaves.len());
                 leaves.push(action);
                 Node::Leaf {
@@ -129,10 +143,15 @@ impl Nodes<Action> {
                     _marker: std::marker::PhantomData,
                 }
             }
-            Entry::Group(Group { name, entries }) => {
+            Entry::Group(Group {
+                name,
+                defaults: group_defaults,
+                entries,
+            }) => {
+                let defaults = group_defaults.within(defaults);
                 let children = entries
                     .into_iter()
-                    .map(|e| Self::convert_entry(e, leaves))
+                    .map(|e| Self::convert_entry(e, &defaults, leaves))
                     .collect();
                 Node::Group { name, children }
             }
@@ -184,6 +203,30 @@ impl<T> Nodes<T> {
         &self.tree
     }
 
+    /// Looks up a group by its path: the index of each node on the way to
+    /// it, starting from the top level.
+    ///
+    /// Returns `None` if the path doesn't lead to a `Group`.
+    #[must_use]
+    pub fn group(&self, path: &[usize]) -> Option<&Node<T>> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.tree.get(*first)?;
+        for index in rest {
+            match node {
+                Node::Group { children, .. } => node = children.get(*index)?,
+                Node::Leaf { .. } => return None,
+            }
+        }
+        node.is_group().then_some(node)
+    }
+
+    /// Iterate the leaf values in `node` with their IDs, depth-first.
+    pub fn leaves<'a>(&'a self, node: &Node<T>) -> impl Iterator<Item = (NodeId, &'a T)> + 'a {
+        node.leaf_ids()
+            .into_iter()
+            .filter_map(|id| self.get(id).map(|value| (id, value)))
+    }
+
     /// Returns the number of leaf nodes.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -200,6 +243,7 @@ impl<T> Nodes<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{Confirm, KeepOpen, Mode, OpenIn};
 
     // === User Story 1: Menu Item Click Lookup (P1) ===
 
@@ -209,12 +253,15 @@ mod tests {
             Entry::Action(Action {
                 name: "Deploy".into(),
                 cmd: "deploy.sh".into(),
+                ..Default::default()
             }),
             Entry::Group(Group {
                 name: "Servers".into(),
+                defaults: Defaults::default(),
                 entries: vec![Entry::Action(Action {
                     name: "Prod".into(),
                     cmd: "ssh prod".into(),
+                    ..Default::default()
                 })],
             }),
         ];
@@ -253,6 +300,7 @@ mod tests {
         let entries = vec![Entry::Action(Action {
             name: "Test".into(),
             cmd: "test".into(),
+            ..Default::default()
         })];
         let nodes = Nodes::from_entries(entries);
 
@@ -269,10 +317,12 @@ mod tests {
             Entry::Action(Action {
                 name: "First".into(),
                 cmd: "first".into(),
+                ..Default::default()
             }),
             Entry::Action(Action {
                 name: "Second".into(),
                 cmd: "second".into(),
+                ..Default::default()
             }),
         ];
 
@@ -292,10 +342,12 @@ mod tests {
             Entry::Action(Action {
                 name: "A".into(),
                 cmd: "a".into(),
+                ..Default::default()
             }),
             Entry::Action(Action {
                 name: "B".into(),
                 cmd: "b".into(),
+                ..Default::default()
             }),
         ];
 
@@ -316,6 +368,7 @@ mod tests {
         let actions = vec![Entry::Action(Action {
             name: "Action".into(),
             cmd: "cmd".into(),
+            ..Default::default()
         })];
         let hosts = vec!["host1".into()];
 
@@ -338,12 +391,15 @@ mod tests {
             Entry::Action(Action {
                 name: "Root".into(),
                 cmd: "root".into(),
+                ..Default::default()
             }),
             Entry::Group(Group {
                 name: "SubMenu".into(),
+                defaults: Defaults::default(),
                 entries: vec![Entry::Action(Action {
                     name: "Child".into(),
                     cmd: "child".into(),
+                    ..Default::default()
                 })],
             }),
         ];
@@ -373,13 +429,17 @@ mod tests {
     fn test_deeply_nested_groups() {
         let entries = vec![Entry::Group(Group {
             name: "Level1".into(),
+            defaults: Defaults::default(),
             entries: vec![Entry::Group(Group {
                 name: "Level2".into(),
+                defaults: Defaults::default(),
                 entries: vec![Entry::Group(Group {
                     name: "Level3".into(),
+                    defaults: Defaults::default(),
                     entries: vec![Entry::Action(Action {
                         name: "Deep".into(),
                         cmd: "deep".into(),
+                        ..Default::default()
                     })],
                 })],
             })],
@@ -449,10 +509,12 @@ mod tests {
             Entry::Action(Action {
                 name: "Same".into(),
                 cmd: "cmd1".into(),
+                ..Default::default()
             }),
             Entry::Action(Action {
                 name: "Same".into(),
                 cmd: "cmd2".into(),
+                ..Default::default()
             }),
         ];
 
@@ -470,6 +532,7 @@ mod tests {
     fn test_empty_group() {
         let entries = vec![Entry::Group(Group {
             name: "EmptyGroup".into(),
+            defaults: Defaults::default(),
             entries: vec![],
         })];
 
@@ -484,6 +547,51 @@ mod tests {
         assert_eq!(nodes.len(), 0);
     }
 
+    #[test]
+    fn test_group_leaves() {
+        let action = |name: &str| {
+            Entry::Action(Action {
+                name: name.into(),
+                cmd: name.into(),
+                ..Default::default()
+            })
+        };
+        let entries = vec![
+            action("Root"),
+            Entry::Group(Group {
+                name: "Servers".into(),
+                defaults: Defaults::default(),
+                entries: vec![
+                    action("Web"),
+                    Entry::Group(Group {
+                        name: "Databases".into(),
+                        defaults: Defaults::default(),
+                        entries: vec![action("Primary"), action("Replica")],
+                    }),
+                ],
+            }),
+        ];
+
+        let nodes = Nodes::from_entries(entries);
+
+        let servers = nodes.group(&[1]).expect("should find group");
+        let names: Vec<_> = nodes
+            .leaves(servers)
+            .map(|(_, a)| a.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Web", "Primary", "Replica"]);
+
+        let databases = nodes.group(&[1, 1]).expect("should find nested group");
+        let ids: Vec<_> = databases.leaf_ids().iter().map(NodeId::index).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        // Paths to leaves or past the end lead nowhere
+        assert!(nodes.group(&[0]).is_none());
+        assert!(nodes.group(&[1, 0]).is_none());
+        assert!(nodes.group(&[1, 5]).is_none());
+        assert!(nodes.group(&[]).is_none());
+    }
+
     // === NodeId Display ===
 
     #[test]
@@ -502,4 +610,137 @@ mod tests {
         assert_eq!(id1, id2);
         assert_ne!(id1, id3);
     }
+
+    #[test]
+    fn test_actions_inherit_group_defaults() {
+        let env = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect()
+        };
+
+        let entries = vec![
+            Entry::Group(Group {
+                name: "Prod".into(),
+                defaults: Defaults {
+                    cwd: Some("~/src/prod".into()),
+                    env: env(&[("AWS_PROFILE", "prod"), ("KUBECONFIG", "~/.kube/prod")]),
+                    env_file: Some("prod.env".into()),
+                    ..Defaults::default()
+                },
+                entries: vec![
+                    Entry::Action(Action {
+                        name: "Web".into(),
+                        cmd: "ssh web".into(),
+                        cwd: Some("~/src/web".into()),
+                        env: env(&[("AWS_PROFILE", "web")]),
+                        ..Default::default()
+                    }),
+                    Entry::Group(Group {
+                        name: "Admin".into(),
+                        defaults: Defaults {
+                            env: env(&[("KUBECONFIG", "~/.kube/admin")]),
+                            ..Defaults::default()
+                        },
+                        entries: vec![Entry::Action(Action {
+                            name: "Db".into(),
+                            cmd: "psql".into(),
+                            ..Default::default()
+                        })],
+                    }),
+                ],
+            }),
+            Entry::Action(Action {
+                name: "Local".into(),
+                cmd: "htop".into(),
+                ..Default::default()
+            }),
+        ];
+
+        let nodes = Nodes::from_entries(entries);
+        let web = nodes.get(NodeId::from_index(0)).unwrap();
+        assert_eq!(web.env["AWS_PROFILE"], "web");
+        assert_eq!(web.env["KUBECONFIG"], "~/.kube/prod");
+        assert_eq!(web.env_file.as_deref(), Some("prod.env"));
+        assert_eq!(web.cwd.as_deref(), Some("~/src/web"));
+
+        let db = nodes.get(NodeId::from_index(1)).unwrap();
+        assert_eq!(db.env["AWS_PROFILE"], "prod");
+        assert_eq!(db.env["KUBECONFIG"], "~/.kube/admin");
+        assert_eq!(db.env_file.as_deref(), Some("prod.env"));
+        assert_eq!(db.cwd.as_deref(), Some("~/src/prod"));
+
+        let local = nodes.get(NodeId::from_index(2)).unwrap();
+        assert!(local.env.is_empty());
+        assert_eq!(local.env_file, None);
+        assert_eq!(local.cwd, None);
+    }
+
+    #[test]
+    fn test_nested_groups_pass_on_options() {
+        let entries = vec![Entry::Group(Group {
+            name: "Prod".into(),
+            defaults: Defaults {
+                terminal: vec!["kitty".into()],
+                keep_open: Some(KeepOpen::Pause),
+                open_in: Some(OpenIn::Tab),
+                mode: Some(Mode::Background),
+                confirm: Some(Confirm::Enabled(true)),
+                ..Defaults::default()
+            },
+            entries: vec![
+                Entry::Action(Action {
+                    name: "Web".into(),
+                    cmd: "ssh web".into(),
+                    ..Default::default()
+                }),
+                Entry::Group(Group {
+                    name: "Admin".into(),
+                    defaults: Defaults {
+                        terminal: vec!["wezterm".into(), "xterm".into()],
+                        open_in: Some(OpenIn::Window),
+                        confirm: Some(Confirm::Message("Really?".into())),
+                        ..Defaults::default()
+                    },
+                    entries: vec![
+                        Entry::Action(Action {
+                            name: "Db".into(),
+                            cmd: "psql".into(),
+                            ..Default::default()
+                        }),
+                        Entry::Action(Action {
+                            name: "Top".into(),
+                            cmd: "htop".into(),
+                            terminal: vec!["ghostty".into()],
+                            keep_open: Some(KeepOpen::Close),
+                            mode: Some(Mode::Terminal),
+                            confirm: Some(Confirm::Enabled(false)),
+                            ..Default::default()
+                        }),
+                    ],
+                }),
+            ],
+        })];
+
+        let nodes = Nodes::from_entries(entries);
+        let web = nodes.get(NodeId::from_index(0)).unwrap();
+        assert_eq!(web.terminal, ["kitty"]);
+        assert_eq!(web.keep_open, Some(KeepOpen::Pause));
+        assert_eq!(web.open_in, Some(OpenIn::Tab));
+        assert_eq!(web.confirm, Some(Confirm::Enabled(true)));
+
+        let db = nodes.get(NodeId::from_index(1)).unwrap();
+        assert_eq!(db.terminal, ["wezterm", "xterm"]);
+        assert_eq!(db.keep_open, Some(KeepOpen::Pause));
+        assert_eq!(db.open_in, Some(OpenIn::Window));
+        assert_eq!(db.mode, Some(Mode::Background));
+        assert_eq!(db.confirm, Some(Confirm::Message("Really?".into())));
+
+        let top = nodes.get(NodeId::from_index(2)).unwrap();
+        assert_eq!(top.terminal, ["ghostty"]);
+        assert_eq!(top.keep_open, Some(KeepOpen::Close));
+        assert_eq!(top.mode, Some(Mode::Terminal));
+        assert_eq!(top.confirm, Some(Confirm::Enabled(false)));
+    }
 }
@@ -1,31 +1,67 @@
-use crate::error::SettingsError;
+use crate::error::{Diagnostic, SettingsError};
+use crate::expand::expand_path;
 use crate::host::Host;
-use crate::loaders::{config, ssh};
+use crate::loaders::config::ConfigContent;
+use crate::loaders::{config, include, ssh};
 use crate::nodes::Nodes;
-use crate::types::Action;
+use crate::types::{Action, CustomTerminal, KeepOpen, OpenIn, Tmux};
+use std::collections::BTreeMap;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Complete application settings loaded from all sources.
 #[derive(Debug, Clone)]
 pub struct Settings {
-    /// Terminal emulator to use for commands.
-    pub terminal: String,
+    /// Terminal emulators to try in order for commands.
+    pub terminal: Vec<String>,
+    /// Terminal emulators defined in the config, by name.
+    pub terminals: BTreeMap<String, CustomTerminal>,
+    /// What happens in the terminal once a command has finished.
+    pub keep_open: KeepOpen,
+    /// Whether commands open in a new window or a tab.
+    pub open_in: OpenIn,
+    /// Settings for opening commands in tmux.
+    pub tmux: Tmux,
+    /// Shell to run commands in, as written in the config. `None` for the
+    /// platform's default.
+    ///
+    /// See [`Settings::shell_program()`] for the expanded path.
+    pub shell: Option<String>,
+    /// Whether to run commands in a login and interactive shell. `None` for
+    /// the platform's default.
+    pub login_shell: Option<bool>,
     /// Editor for opening config files.
     pub editor: String,
-    /// Actions from ~/.xshuttle.json with O(1) ID-based lookup.
+    /// Whether to show desktop notifications.
+    pub notifications: bool,
+    /// Actions from the config file with O(1) ID-based lookup.
     pub actions: Nodes<Action>,
     /// SSH hosts from ~/.ssh/config with O(1) ID-based lookup.
     pub hosts: Nodes<Host>,
+    /// Files and directories the settings were loaded from, for change detection.
+    pub sources: Vec<PathBuf>,
+    /// Problems that caused parts of the config to be skipped.
+    ///
+    /// Always empty for settings from [`Settings::load_from()`].
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
-            terminal: Self::DEFAULT_TERMINAL.to_string(),
+            terminal: vec![Self::DEFAULT_TERMINAL.to_string()],
+            terminals: BTreeMap::new(),
+            keep_open: KeepOpen::default(),
+            open_in: OpenIn::default(),
+            tmux: Tmux::default(),
+            shell: None,
+            login_shell: None,
             editor: Self::DEFAULT_EDITOR.to_string(),
+            notifications: true,
             actions: Nodes::from_entries(vec![]),
             hosts: Nodes::from_hostnames(vec![]),
+            sources: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 }
@@ -36,10 +72,26 @@ impl Settings {
     /// Default editor value when not specified in config.
     pub const DEFAULT_EDITOR: &'static str = "default";
 
+    /// Load settings from all sources, using the default config path.
+    ///
+    /// See [`Settings::config_path()`] for how the path is chosen and
+    /// [`Settings::load_from()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the home directory cannot be determined, or if
+    /// loading from the config path fails.
+    pub fn load() -> Result<Self, SettingsError> {
+        let path = Self::config_path().ok_or(SettingsError::NoHomeDir)?;
+        Self::load_from(&path)
+    }
+
     /// Load settings from all sources.
     ///
     /// This loads:
-    /// - Configuration from `~/.xshuttle.json` (uses defaults if missing)
+    /// - Configuration from `path` (uses defaults if missing), the files it
+    ///   includes, the fragments in its `conf.d` directory and its
+    ///   `.local.json` override file
     /// - SSH hosts from `~/.ssh/config` (empty if file doesn't exist)
     ///
     /// Warnings for non-fatal issues are logged via the `log` crate.
@@ -48,42 +100,134 @@ impl Settings {
     ///
     /// Returns an error if:
     /// - Home directory cannot be determined
-    /// - Config file exists but is invalid JSON or fails validation
+    /// - Config file or an included file is invalid JSON or fails validation
+    /// - Config files include each other in a cycle
     /// - SSH config file exists but cannot be parsed
-    pub fn load() -> Result<Self, SettingsError> {
-        let config = config::load()?.unwrap_or_default();
+    pub fn load_from(path: &Path) -> Result<Self, SettingsError> {
+        let config = config::load(path)?.unwrap_or_default();
         let raw_hosts = ssh::parse_ssh_config()?;
 
-        Ok(Settings {
+        Ok(Self::assemble(path, config, raw_hosts, Vec::new()))
+    }
+
+    /// Load settings from all sources, skipping whatever is invalid.
+    ///
+    /// Loads the same sources as [`Settings::load_from()`], but never fails:
+    /// - Actions, groups and keys that fail validation are dropped, keeping
+    ///   their valid siblings
+    /// - Config files that can't be read or parsed are skipped, as are
+    ///   includes that are missing or form a cycle
+    /// - An invalid SSH config leaves the host list empty, and an invalid
+    ///   config file leaves the hosts untouched
+    ///
+    /// Every problem is recorded in [`Settings::diagnostics`].
+    pub fn load_lenient(path: &Path) -> Self {
+        let (config, diagnostics) = config::load_lenient(path);
+        Self::lenient(
+            path,
+            config.unwrap_or_default(),
+            ssh::parse_ssh_config(),
+            diagnostics,
+        )
+    }
+
+    fn lenient(
+        path: &Path,
+        config: ConfigContent,
+        raw_hosts: Result<Vec<String>, SettingsError>,
+        mut diagnostics: Vec<Diagnostic>,
+    ) -> Self {
+        let raw_hosts = raw_hosts.unwrap_or_else(|e| {
+            diagnostics.push(Diagnostic::from_error(
+                ssh::ssh_config_path().as_deref(),
+                &e,
+            ));
+            Vec::new()
+        });
+
+        Self::assemble(path, config, raw_hosts, diagnostics)
+    }
+
+    fn assemble(
+        path: &Path,
+        config: ConfigContent,
+        raw_hosts: Vec<String>,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Self {
+        let mut sources = Self::watch_paths(path);
+        sources.extend(config.sources);
+
+        Settings {
             terminal: config
                 .terminal
-                .unwrap_or_else(|| Self::DEFAULT_TERMINAL.to_string()),
+                .unwrap_or_else(|| vec![Self::DEFAULT_TERMINAL.to_string()]),
+            terminals: config.terminals,
+            keep_open: config.keep_open.unwrap_or_default(),
+            open_in: config.open_in.unwrap_or_default(),
+            tmux: config.tmux.unwrap_or_default(),
+            shell: config.shell,
+            login_shell: config.login_shell,
             editor: config
                 .editor
                 .unwrap_or_else(|| Self::DEFAULT_EDITOR.to_string()),
+            notifications: config.notifications.unwrap_or(true),
             actions: Nodes::from_entries(config.actions.unwrap_or_default()),
             hosts: Nodes::from_hostnames(raw_hosts),
-        })
+            sources,
+            diagnostics,
+        }
+    }
+
+    /// Returns the shell to run commands in, with `~` and environment
+    /// variables expanded.
+    pub fn shell_program(&self) -> Option<PathBuf> {
+        self.shell.as_deref().map(expand_path)
     }
 
     /// Get the path to the main config file.
+    ///
+    /// Uses `$XSHUTTLE_CONFIG` if set, then `$XDG_CONFIG_HOME/xshuttle/config.json`
+    /// if it exists, and falls back to `~/.xshuttle.json`.
     pub fn config_path() -> Option<PathBuf> {
         config::config_path()
     }
 
-    /// Ensure the config file exists, creating a default one if missing.
+    /// Get the directory for the output of background actions.
+    ///
+    /// Uses `$XDG_STATE_HOME/xshuttle/logs` (`~/.local/state/xshuttle/logs`)
+    /// where there is a state directory, and the cache directory otherwise.
+    pub fn log_dir() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::cache_dir)
+            .map(|dir| dir.join("xshuttle").join("logs"))
+    }
+
+    /// Get the files whose changes should trigger a settings reload.
+    ///
+    /// Includes the config file at `path`, its fragment directory, its local
+    /// override file and `~/.ssh/config`, whether or not they currently
+    /// exist. Files pulled in through `include` are only known after
+    /// loading; see [`Settings::sources`].
+    pub fn watch_paths(path: &Path) -> Vec<PathBuf> {
+        [
+            path.to_path_buf(),
+            include::fragments_dir(path),
+            config::local_path(path),
+        ]
+        .into_iter()
+        .chain(ssh::ssh_config_path())
+        .collect()
+    }
+
+    /// Ensure the config file at `path` exists, creating a default one if missing.
     ///
     /// # Errors
     ///
-    /// Returns an error if the home directory cannot be determined or if
-    /// writing the default config file fails.
-    pub fn ensure_config_exists() -> Result<PathBuf, io::Error> {
-        config::ensure_config_exists().map_err(|e| match e {
+    /// Returns an error if creating the parent directory or writing the
+    /// default config file fails.
+    pub fn ensure_config_exists(path: &Path) -> Result<(), io::Error> {
+        config::ensure_config_exists(path).map_err(|e| match e {
             SettingsError::ConfigIo(io_err) => io_err,
-            SettingsError::NoHomeDir => io::Error::new(
-                io::ErrorKind::NotFound,
-                "could not determine home directory",
-            ),
             _ => io::Error::other(e.to_string()),
         })
     }
@@ -92,22 +236,113 @@ impl Settings {
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn test_config_path_returns_some() {
-        // This test assumes a home directory exists
-        let path = Settings::config_path();
-        if let Some(p) = path {
-            assert!(p.ends_with(".xshuttle.json"));
-        }
-    }
+    use crate::error::Severity;
 
     #[test]
     fn test_settings_default() {
         let settings = Settings::default();
-        assert_eq!(settings.terminal, Settings::DEFAULT_TERMINAL);
+        assert_eq!(settings.terminal, vec![Settings::DEFAULT_TERMINAL]);
         assert_eq!(settings.editor, Settings::DEFAULT_EDITOR);
         assert!(settings.actions.is_empty());
         assert!(settings.hosts.is_empty());
     }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("xshuttle-settings-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_lenient_keeps_valid_actions() {
+        let dir = temp_dir("lenient");
+        let path = dir.join("config.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "terminal": "kitty",
+                "actions": [
+                    {"name": "Good", "cmd": "echo good"},
+                    {"name": "Bad", "cmd": 42},
+                    {"Prod": [{"name": "Web", "cmd": "ssh web"}, {"name": "Db"}]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let (config, diagnostics) = config::load_lenient(&path);
+        let settings = Settings::lenient(&path, config.unwrap(), Ok(vec![]), diagnostics);
+
+        assert_eq!(settings.terminal, vec!["kitty"]);
+        let names: Vec<_> = settings
+            .actions
+            .iter()
+            .map(|(_, action)| action.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Good", "Web"]);
+
+        assert_eq!(settings.diagnostics.len(), 2);
+        assert!(
+            settings
+                .diagnostics
+                .iter()
+                .all(|d| d.severity == Severity::Warning && d.file.as_deref() == Some(&*path))
+        );
+        assert_eq!(settings.diagnostics[0].location.map(|l| l.line), Some(5));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ssh_error_keeps_actions() {
+        let path = PathBuf::from("/nonexistent/config.json");
+        let config = ConfigContent {
+            actions: Some(vec![crate::types::Entry::Action(Action {
+                name: "A".to_string(),
+                cmd: "a".to_string(),
+                ..Default::default()
+            })]),
+            ..ConfigContent::default()
+        };
+
+        let settings = Settings::lenient(
+            &path,
+            config,
+            Err(SettingsError::SshParse("bad".to_string())),
+            Vec::new(),
+        );
+        assert_eq!(settings.actions.len(), 1);
+        assert!(settings.hosts.is_empty());
+        assert_eq!(settings.diagnostics.len(), 1);
+        assert_eq!(settings.diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_config_error_keeps_hosts() {
+        let dir = temp_dir("broken");
+        let path = dir.join("config.json");
+        std::fs::write(&path, r#"{"actions": [}"#).unwrap();
+
+        let (config, diagnostics) = config::load_lenient(&path);
+        let settings = Settings::lenient(
+            &path,
+            config.unwrap_or_default(),
+            Ok(vec!["web".to_string()]),
+            diagnostics,
+        );
+        assert!(settings.actions.is_empty());
+        assert_eq!(settings.hosts.len(), 1);
+
+        let diagnostic = &settings.diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.location.map(|l| l.line), Some(1));
+        assert!(
+            diagnostic.message.starts_with("invalid JSON"),
+            "{diagnostic}"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,60 @@
+//! "Did you mean" suggestions for misspelled names.
+
+/// Returns the candidate closest to `input`, if it is close enough to be a
+/// likely typo.
+///
+/// Comparison is case-insensitive and tolerates roughly one edit per three
+/// characters (at least one), counting transpositions as a single edit.
+pub fn did_you_mean<'a, I>(input: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let input = input.to_lowercase();
+
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            let distance = strsim::damerau_levenshtein(&input, &candidate.to_lowercase());
+            (distance, candidate)
+        })
+        .filter(|&(distance, candidate)| {
+            distance > 0 && distance <= (candidate.chars().count() / 3).max(1)
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TERMINALS: &[&str] = &["alacritty", "kitty", "konsole", "xterm"];
+
+    #[test]
+    fn test_suggests_close_match() {
+        assert_eq!(
+            did_you_mean("alacrity", TERMINALS.iter().copied()),
+            Some("alacritty")
+        );
+        assert_eq!(
+            did_you_mean("kityt", TERMINALS.iter().copied()),
+            Some("kitty")
+        );
+        assert_eq!(did_you_mean("nmae", ["name", "cmd"]), Some("name"));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert_eq!(
+            did_you_mean("XTrem", TERMINALS.iter().copied()),
+            Some("xterm")
+        );
+    }
+
+    #[test]
+    fn test_no_suggestion_for_distant_or_exact() {
+        assert_eq!(did_you_mean("wezterm", TERMINALS.iter().copied()), None);
+        assert_eq!(did_you_mean("kitty", TERMINALS.iter().copied()), None);
+        assert_eq!(did_you_mean("x", ["cmd"]), None);
+    }
+}
@@ -0,0 +1,375 @@
+//! Commands with placeholders that are asked for before launching.
+//!
+//! Two kinds of placeholder are recognized in a command:
+//!
+//! - `{input:Label}` asks for free text
+//! - `{choice:Label|a,b,c}` asks to pick one of the listed options
+//!
+//! Placeholders with the same label are asked for once. Values are
+//! shell-quoted when they're filled in, so each becomes one word of the
+//! command whatever it contains. That only holds outside quotes, so
+//! placeholders inside quotes or right after a backslash are rejected.
+//! Any other braces, as in `${HOME}` or `awk '{print $1}'`, are left alone;
+//! `{{input:` and `{{choice:` stand for a literal `{input:` and `{choice:`.
+
+use std::collections::BTreeMap;
+
+const INPUT: &str = "input:";
+const CHOICE: &str = "choice:";
+
+/// A value to ask for before running a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placeholder {
+    /// Free text.
+    Input {
+        /// What to ask for.
+        label: String,
+    },
+    /// One of a list of options.
+    Choice {
+        /// What to ask for.
+        label: String,
+        /// The options to pick from, in order.
+        options: Vec<String>,
+    },
+}
+
+impl Placeholder {
+    /// Returns what to ask for.
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Input { label } | Self::Choice { label, .. } => label,
+        }
+    }
+
+    /// Returns the options to pick from, empty for free text.
+    pub fn options(&self) -> &[String] {
+        match self {
+            Self::Input { .. } => &[],
+            Self::Choice { options, .. } => options,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    /// Index into the template's placeholders.
+    Value(usize),
+}
+
+/// Where the shell is in a command, as far as quoting goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Unquoted,
+    /// After a backslash outside quotes.
+    Escaped,
+    SingleQuoted,
+    DoubleQuoted,
+    /// After a backslash inside double quotes.
+    DoubleEscaped,
+}
+
+impl Context {
+    /// Returns the context after the shell has read `text`.
+    fn after(self, text: &str) -> Self {
+        text.chars().fold(self, |context, c| match (context, c) {
+            (Self::Unquoted, '\\') => Self::Escaped,
+            (Self::Unquoted, '\'') => Self::SingleQuoted,
+            (Self::Unquoted, '"') | (Self::DoubleEscaped, _) => Self::DoubleQuoted,
+            (Self::Escaped, _) | (Self::SingleQuoted, '\'') | (Self::DoubleQuoted, '"') => {
+                Self::Unquoted
+            }
+            (Self::DoubleQuoted, '\\') => Self::DoubleEscaped,
+            (context, _) => context,
+        })
+    }
+}
+
+/// A command split into literal text and placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+    placeholders: Vec<Placeholder>,
+}
+
+impl Template {
+    /// Parses a command.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a placeholder is unterminated, has no label, is a
+    /// choice without options, reuses a label for something else, or is
+    /// inside quotes or after a backslash.
+    pub fn parse(command: &str) -> Result<Self, String> {
+        let mut template = Self {
+            parts: Vec::new(),
+            placeholders: Vec::new(),
+        };
+        let mut text = String::new();
+        let mut rest = command;
+        let mut context = Context::Unquoted;
+
+        while let Some(start) = rest.find('{') {
+            let (before, from_brace) = rest.split_at(start);
+            text.push_str(before);
+            let after = &from_brace[1..];
+
+            // A doubled brace keeps a placeholder literal
+            if let Some(escaped) = after.strip_prefix('{')
+                && is_placeholder(escaped)
+            {
+                text.push('{');
+                rest = escaped;
+                continue;
+            }
+            if !is_placeholder(after) {
+                text.push('{');
+                rest = after;
+                continue;
+            }
+
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unterminated placeholder '{{{after}'"))?;
+            let inside = &after[..end];
+            let placeholder = parse_placeholder(inside)?;
+
+            // Quoting the value only keeps it one word outside quotes
+            context = context.after(&text);
+            match context {
+                Context::Unquoted => {}
+                Context::Escaped => {
+                    return Err(format!("placeholder '{{{inside}}}' follows a backslash"));
+                }
+                _ => {
+                    return Err(format!(
+                        "placeholder '{{{inside}}}' is inside quotes, but values are quoted already"
+                    ));
+                }
+            }
+            let index = template.add(placeholder)?;
+
+            if !text.is_empty() {
+                template.parts.push(Part::Text(std::mem::take(&mut text)));
+            }
+            template.parts.push(Part::Value(index));
+            rest = &after[end + 1..];
+        }
+
+        text.push_str(rest);
+        if !text.is_empty() {
+            template.parts.push(Part::Text(text));
+        }
+        Ok(template)
+    }
+
+    /// Adds a placeholder, returning its index, or the index of the earlier
+    /// one with the same label.
+    fn add(&mut self, placeholder: Placeholder) -> Result<usize, String> {
+        let label = placeholder.label();
+        match self.placeholders.iter().position(|p| p.label() == label) {
+            Some(index) if self.placeholders[index] == placeholder => Ok(index),
+            Some(_) => Err(format!(
+                "placeholder '{label}' is defined differently twice"
+            )),
+            None => {
+                self.placeholders.push(placeholder);
+                Ok(self.placeholders.len() - 1)
+            }
+        }
+    }
+
+    /// Returns the values to ask for, each label once, in order of first
+    /// appearance.
+    pub fn placeholders(&self) -> &[Placeholder] {
+        &self.placeholders
+    }
+
+    /// Returns `true` if there's nothing to ask for.
+    pub fn is_literal(&self) -> bool {
+        self.placeholders.is_empty()
+    }
+
+    /// Returns the command with every placeholder replaced by the shell-quoted
+    /// value for its label. Missing values become an empty argument.
+    pub fn render(&self, values: &BTreeMap<String, String>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Value(index) => {
+                    let label = self.placeholders[*index].label();
+                    let value = values.get(label).map_or("", String::as_str);
+                    shell_words::quote(value).into_owned()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns `true` if `s`, which follows a `{`, starts a placeholder.
+fn is_placeholder(s: &str) -> bool {
+    s.starts_with(INPUT) || s.starts_with(CHOICE)
+}
+
+/// Parses the inside of a placeholder, between the braces.
+fn parse_placeholder(inside: &str) -> Result<Placeholder, String> {
+    let label_of = |label: &str| {
+        let label = label.trim();
+        if label.is_empty() {
+            Err(format!("placeholder '{{{inside}}}' has no label"))
+        } else {
+            Ok(label.to_string())
+        }
+    };
+
+    if let Some(label) = inside.strip_prefix(INPUT) {
+        return Ok(Placeholder::Input {
+            label: label_of(label)?,
+        });
+    }
+
+    let choice = inside.strip_prefix(CHOICE).unwrap_or(inside);
+    let (label, options) = choice.split_once('|').ok_or_else(|| {
+        format!("choice '{{{inside}}}' has no options, as in '{{choice:Label|a,b}}'")
+    })?;
+    let options: Vec<String> = options.split(',').map(|o| o.trim().to_string()).collect();
+    if options.iter().any(String::is_empty) {
+        return Err(format!("choice '{{{inside}}}' has an empty option"));
+    }
+
+    Ok(Placeholder::Choice {
+        label: label_of(label)?,
+        options,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_placeholders() {
+        let template =
+            Template::parse("ssh {choice:Region|eu, us,ap}-bastion -- logs {input:Pod name}")
+                .unwrap();
+        assert_eq!(
+            template.placeholders(),
+            [
+                Placeholder::Choice {
+                    label: "Region".into(),
+                    options: vec!["eu".into(), "us".into(), "ap".into()],
+                },
+                Placeholder::Input {
+                    label: "Pod name".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_quotes_values() {
+        let template = Template::parse("kubectl logs {input:Pod} -n {input:Pod}").unwrap();
+        assert_eq!(template.placeholders().len(), 1);
+        assert_eq!(
+            template.render(&values(&[("Pod", "web; rm -rf ~")])),
+            "kubectl logs 'web; rm -rf ~' -n 'web; rm -rf ~'"
+        );
+        assert_eq!(template.render(&values(&[])), "kubectl logs '' -n ''");
+    }
+
+    /// Runs a rendered command in `sh` and returns what it prints.
+    fn run(command: &str) -> String {
+        let output = std::process::Command::new("sh")
+            .args(["-c", command])
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_rendered_values_are_not_run() {
+        let template = Template::parse("printf '%s|' {input:A} \"$HOME\"{input:B}'x'").unwrap();
+        for value in [
+            "$(echo injected)",
+            "`echo injected`",
+            "'; echo injected; '",
+            "\"; echo injected; \"",
+            "\\",
+            "",
+        ] {
+            let command = template.render(&values(&[("A", value), ("B", value)]));
+            let home = std::env::var("HOME").unwrap_or_default();
+            assert_eq!(
+                run(&command),
+                format!("{value}|{home}{value}x|"),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_placeholders_in_quotes_rejected() {
+        for command in [
+            "echo '{input:Name}'",
+            "echo \"{input:Name}\"",
+            "echo 'it''s {input:Name}'",
+            "echo \"say \\\"{input:Name}\\\"\"",
+        ] {
+            let result = Template::parse(command);
+            assert!(
+                result.as_ref().is_err_and(|e| e.contains("inside quotes")),
+                "{command}: {result:?}"
+            );
+        }
+        assert!(Template::parse("echo \\{input:Name}").is_err_and(|e| e.contains("backslash")));
+
+        // Quotes and backslashes that are closed or escaped before it are fine
+        for command in [
+            "echo 'a' \"b\" {input:Name}",
+            "echo it\\'s {input:Name}",
+            "echo \"say \\\"hi\\\"\" {input:Name}",
+            "echo \\\\{input:Name}",
+        ] {
+            let result = Template::parse(command);
+            assert!(result.is_ok(), "{command}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn test_other_braces_are_literal() {
+        let command = "awk '{print $1}' ${HOME}/log {{input:x} {unknown:y}";
+        let template = Template::parse(command).unwrap();
+        assert!(template.is_literal());
+        assert_eq!(
+            template.render(&values(&[])),
+            "awk '{print $1}' ${HOME}/log {input:x} {unknown:y}"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (command, error) in [
+            ("echo {input:Name", "unterminated"),
+            ("echo {input: }", "no label"),
+            ("echo {choice:Region}", "no options"),
+            ("echo {choice:Region|eu,,us}", "empty option"),
+            ("echo {choice:|eu,us}", "no label"),
+            ("echo {input:X} {choice:X|a,b}", "differently"),
+        ] {
+            let result = Template::parse(command);
+            assert!(
+                result.as_ref().is_err_and(|e| e.contains(error)),
+                "{command}: {result:?}"
+            );
+        }
+    }
+}
@@ -1,28 +1,342 @@
-use serde::de::{self, Deserializer, MapAccess, Visitor};
+use crate::error::SettingsError;
+use crate::expand::expand_path;
+use crate::loaders::dotenv;
+use crate::template::Template;
+use serde::de::{self, Deserializer};
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 /// A single executable menu item with a display name and command.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Action {
     pub name: String,
     pub cmd: String,
+    /// Directory to run the command in, as written in the config.
+    ///
+    /// See [`Action::working_dir()`] for the expanded path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Environment variables to set for the command.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// A `.env` file to load environment variables from, as written in the
+    /// config. Variables in `env` take precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    /// Terminals to try in order instead of the global `terminal` setting.
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub terminal: Vec<String>,
+    /// What happens in the terminal once the command has finished, instead
+    /// of the global `keep_open` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_open: Option<KeepOpen>,
+    /// Whether to open a new window or a tab, instead of the global
+    /// `open_in` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_in: Option<OpenIn>,
+    /// Whether the command runs in a terminal or in the background. `None`
+    /// for a terminal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<Mode>,
+    /// Whether to ask before running the command, optionally with the
+    /// question to ask.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<Confirm>,
+}
+
+impl Action {
+    /// Returns the directory to run the command in, with `~` and
+    /// environment variables expanded.
+    pub fn working_dir(&self) -> Option<PathBuf> {
+        self.cwd.as_deref().map(expand_path)
+    }
+
+    /// Returns the environment variables to set for the command.
+    ///
+    /// Variables are read from `env_file` first, then overridden by `env`.
+    /// A relative `env_file` is resolved against the working directory, or
+    /// the home directory if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the env file can't be read or parsed.
+    pub fn environment(&self) -> Result<BTreeMap<String, String>, SettingsError> {
+        let mut env = BTreeMap::new();
+
+        if let Some(env_file) = &self.env_file {
+            let path = expand_path(env_file);
+            let path = match self.working_dir().or_else(dirs::home_dir) {
+                Some(base) if path.is_relative() => base.join(path),
+                _ => path,
+            };
+            env.extend(dotenv::load(&path)?);
+        }
+
+        env.extend(self.env.clone());
+        Ok(env)
+    }
+
+    /// Returns the command split into literal text and the placeholders to
+    /// ask for before running it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a placeholder is malformed. Loading the config
+    /// skips such actions, so this only fails for actions built in code.
+    pub fn template(&self) -> Result<Template, String> {
+        Template::parse(&self.cmd)
+    }
+
+    /// Fills in unset options from a group's defaults.
+    pub(crate) fn inherit(&mut self, defaults: &Defaults) {
+        if self.cwd.is_none() {
+            self.cwd.clone_from(&defaults.cwd);
+        }
+        for (key, value) in &defaults.env {
+            self.env.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        if self.env_file.is_none() {
+            self.env_file.clone_from(&defaults.env_file);
+        }
+        if self.terminal.is_empty() {
+            self.terminal.clone_from(&defaults.terminal);
+        }
+        if self.keep_open.is_none() {
+            self.keep_open = defaults.keep_open;
+        }
+        if self.open_in.is_none() {
+            self.open_in = defaults.open_in;
+        }
+        if self.mode.is_none() {
+            self.mode = defaults.mode;
+        }
+        if self.confirm.is_none() {
+            self.confirm.clone_from(&defaults.confirm);
+        }
+    }
+}
+
+/// What happens in the terminal once a command has finished.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepOpen {
+    /// Start the user's shell.
+    #[default]
+    Shell,
+    /// Close the terminal.
+    Close,
+    /// Close the terminal if the command succeeded, and pause otherwise.
+    CloseOnSuccess,
+    /// Show the exit code and wait for a key press.
+    Pause,
+}
+
+/// Whether an action asks before it runs: `true`, or the question to ask.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Confirm {
+    /// Ask a generic question if `true`.
+    Enabled(bool),
+    /// Ask this question.
+    Message(String),
+}
+
+impl Confirm {
+    /// Returns the question to ask before running the action named `name`,
+    /// or `None` if it runs without asking.
+    pub fn message(&self, name: &str) -> Option<String> {
+        match self {
+            Self::Enabled(true) => Some(format!("Run '{name}'?")),
+            Self::Enabled(false) => None,
+            Self::Message(message) => Some(message.clone()),
+        }
+    }
+}
+
+/// How an action's command runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// In a terminal.
+    #[default]
+    Terminal,
+    /// Without a terminal, with its output going to a log file.
+    Background,
+}
+
+/// Where a command opens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenIn {
+    /// A new terminal window.
+    #[default]
+    Window,
+    /// A new tab in a running terminal, or a window if there's none.
+    Tab,
+}
+
+/// A terminal emulator defined in the config, in addition to the built-in
+/// ones.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomTerminal {
+    /// Binary to run, as written in the config.
+    ///
+    /// See [`CustomTerminal::program()`] for the expanded path.
+    pub bin: String,
+    /// Arguments, with `{cmd}`, `{cwd}` and `{title}` placeholders.
+    pub args: Vec<String>,
+}
+
+impl CustomTerminal {
+    /// Returns the binary to run, with `~` and environment variables
+    /// expanded.
+    pub fn program(&self) -> PathBuf {
+        expand_path(&self.bin)
+    }
+}
+
+/// Settings for opening commands in tmux.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tmux {
+    /// Session to open commands in. `None` for the default session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Socket name of the tmux server, as with `tmux -L`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+    /// Session on SSH hosts to attach to, or create, when connecting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reattach: Option<String>,
+    /// Whether the panes opened by "Open all" take input together. `None`
+    /// for the default, which is to synchronize them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synchronize_panes: Option<bool>,
+}
+
+/// Options a group passes on to every action in it.
+///
+/// Actions keep the values they set themselves, and nested groups take
+/// precedence over the groups around them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Defaults {
+    /// Directory to run commands in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Environment variables, merged with the action's own.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// A `.env` file for actions that don't name one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    /// Terminals to try in order.
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub terminal: Vec<String>,
+    /// What happens in the terminal once the command has finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_open: Option<KeepOpen>,
+    /// Whether to open a new window or a tab.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_in: Option<OpenIn>,
+    /// Whether commands run in a terminal or in the background.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<Mode>,
+    /// Whether to ask before running commands, optionally with the question
+    /// to ask.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<Confirm>,
+}
+
+impl Defaults {
+    /// Returns `true` if no defaults are set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns these defaults layered over the defaults of an outer group.
+    pub(crate) fn within(&self, outer: &Defaults) -> Defaults {
+        let mut env = outer.env.clone();
+        env.extend(self.env.clone());
+        Defaults {
+            cwd: self.cwd.clone().or_else(|| outer.cwd.clone()),
+            env,
+            env_file: self.env_file.clone().or_else(|| outer.env_file.clone()),
+            terminal: if self.terminal.is_empty() {
+                outer.terminal.clone()
+            } else {
+                self.terminal.clone()
+            },
+            keep_open: self.keep_open.or(outer.keep_open),
+            open_in: self.open_in.or(outer.open_in),
+            mode: self.mode.or(outer.mode),
+            confirm: self.confirm.clone().or_else(|| outer.confirm.clone()),
+        }
+    }
+
+    /// Layers the defaults of a local override onto these.
+    pub(crate) fn overlay(&mut self, overlay: Defaults) {
+        self.cwd = overlay.cwd.or(self.cwd.take());
+        self.env.extend(overlay.env);
+        self.env_file = overlay.env_file.or(self.env_file.take());
+        if !overlay.terminal.is_empty() {
+            self.terminal = overlay.terminal;
+        }
+        self.keep_open = overlay.keep_open.or(self.keep_open.take());
+        self.open_in = overlay.open_in.or(self.open_in.take());
+        self.mode = overlay.mode.or(self.mode.take());
+        self.confirm = overlay.confirm.or(self.confirm.take());
+    }
 }
 
 /// A named group containing nested entries.
-/// Serializes to/from JSON as `{"GroupName": [...]}`
-#[derive(Debug, Clone)]
+///
+/// Serializes to/from JSON as `{"GroupName": [...]}`, or, to set defaults,
+/// as `{"group": "GroupName", "defaults": {...}, "entries": [...]}`.
+#[derive(Debug, Clone, Default)]
 pub struct Group {
     pub name: String,
+    /// Options inherited by the actions in the group.
+    pub defaults: Defaults,
     pub entries: Vec<Entry>,
 }
 
+/// The object form of a group.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct GroupObject {
+    group: String,
+    #[serde(default, skip_serializing_if = "Defaults::is_empty")]
+    defaults: Defaults,
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
 impl Serialize for Group {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
+        if !self.defaults.is_empty() {
+            return GroupObject {
+                group: self.name.clone(),
+                defaults: self.defaults.clone(),
+                entries: self.entries.clone(),
+            }
+            .serialize(serializer);
+        }
+
         let mut map = serializer.serialize_map(Some(1))?;
         map.serialize_entry(&self.name, &self.entries)?;
         map.end()
@@ -34,36 +348,64 @@ impl<'de> Deserialize<'de> for Group {
     where
         D: Deserializer<'de>,
     {
-        struct GroupVisitor;
+        let map = Map::<String, Value>::deserialize(deserializer)?;
 
-        impl<'de> Visitor<'de> for GroupVisitor {
-            type Value = Group;
+        // A group named "group" has a list, not a name, as its value
+        if matches!(map.get("group"), Some(Value::String(_))) {
+            let object: GroupObject =
+                serde_json::from_value(Value::Object(map)).map_err(de::Error::custom)?;
+            return Ok(Group {
+                name: object.group,
+                defaults: object.defaults,
+                entries: object.entries,
+            });
+        }
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a map with a single key (group name) and array value")
-            }
+        let mut map = map.into_iter();
+        let (name, entries) = map
+            .next()
+            .ok_or_else(|| de::Error::custom("expected non-empty map for group"))?;
 
-            fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
-            where
-                M: MapAccess<'de>,
-            {
-                let (name, entries): (String, Vec<Entry>) = access
-                    .next_entry()?
-                    .ok_or_else(|| de::Error::custom("expected non-empty map for group"))?;
-
-                // Ensure no extra keys
-                if access.next_key::<String>()?.is_some() {
-                    return Err(de::Error::custom(
-                        "group must have exactly one key (the group name)",
-                    ));
-                }
-
-                Ok(Group { name, entries })
-            }
+        // Ensure no extra keys
+        if map.next().is_some() {
+            return Err(de::Error::custom(
+                "group must have exactly one key (the group name)",
+            ));
         }
 
-        deserializer.deserialize_map(GroupVisitor)
+        Ok(Group {
+            name,
+            defaults: Defaults::default(),
+            entries: serde_json::from_value(entries).map_err(de::Error::custom)?,
+        })
+    }
+}
+
+/// Deserializes either a single string or a list of strings.
+pub(crate) fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
     }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Deserializes either a single string or a list of strings into `Some`,
+/// for fields that must tell an unset value apart.
+pub(crate) fn some_one_or_many<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    one_or_many(deserializer).map(Some)
 }
 
 /// An entry in the menu - either an action or a group.
@@ -83,6 +425,7 @@ mod tests {
         let action = Action {
             name: "Test".to_string(),
             cmd: "echo hello".to_string(),
+            ..Default::default()
         };
         let json = serde_json::to_string(&action).unwrap();
         assert!(json.contains("Test"));
@@ -93,9 +436,11 @@ mod tests {
     fn test_group_serialization() {
         let group = Group {
             name: "Production".to_string(),
+            defaults: Defaults::default(),
             entries: vec![Entry::Action(Action {
                 name: "Server".to_string(),
                 cmd: "ssh prod".to_string(),
+                ..Default::default()
             })],
         };
         let json = serde_json::to_string(&group).unwrap();
@@ -109,10 +454,164 @@ mod tests {
         assert!(matches!(entry, Entry::Action(_)));
     }
 
+    #[test]
+    fn test_action_terminal_one_or_many() {
+        let json = r#"{"name": "Top", "cmd": "htop", "terminal": "kitty"}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(action.terminal, vec!["kitty"]);
+
+        let json = r#"{"name": "Top", "cmd": "htop", "terminal": ["ghostty", "xterm"]}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(action.terminal, vec!["ghostty", "xterm"]);
+    }
+
+    #[test]
+    fn test_action_keep_open() {
+        let json = r#"{"name": "Sync", "cmd": "make sync", "keep_open": "close_on_success"}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(action.keep_open, Some(KeepOpen::CloseOnSuccess));
+
+        let json = serde_json::to_string(&Action::default()).unwrap();
+        assert!(!json.contains("keep_open"));
+    }
+
+    #[test]
+    fn test_action_mode() {
+        let json = r#"{"name": "Sync dotfiles", "cmd": "make sync", "mode": "background"}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(action.mode, Some(Mode::Background));
+
+        let json = serde_json::to_string(&Action::default()).unwrap();
+        assert!(!json.contains("mode"));
+    }
+
+    #[test]
+    fn test_action_confirm() {
+        let json = r#"{"name": "Restart prod", "cmd": "make restart", "confirm": true}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            action.confirm.unwrap().message(&action.name).as_deref(),
+            Some("Run 'Restart prod'?")
+        );
+
+        let json = r#"{"name": "Drop", "cmd": "make drop", "confirm": "Really drop staging DB?"}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            action.confirm.unwrap().message(&action.name).as_deref(),
+            Some("Really drop staging DB?")
+        );
+
+        let json = r#"{"name": "Top", "cmd": "htop", "confirm": false}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(action.confirm.unwrap().message(&action.name), None);
+    }
+
+    #[test]
+    fn test_action_open_in() {
+        let json = r#"{"name": "Logs", "cmd": "make logs", "open_in": "tab"}"#;
+        let action: Action = serde_json::from_str(json).unwrap();
+        assert_eq!(action.open_in, Some(OpenIn::Tab));
+    }
+
     #[test]
     fn test_entry_untagged_group() {
         let json = r#"{"MyGroup": [{"name": "Test", "cmd": "echo"}]}"#;
         let entry: Entry = serde_json::from_str(json).unwrap();
         assert!(matches!(entry, Entry::Group(_)));
     }
+
+    #[test]
+    fn test_group_object_form() {
+        let json = r#"{
+            "group": "Staging",
+            "defaults": {"env": {"AWS_PROFILE": "staging"}},
+            "entries": [{"name": "Web", "cmd": "ssh web"}]
+        }"#;
+        let Entry::Group(group) = serde_json::from_str(json).unwrap() else {
+            panic!("Expected group");
+        };
+        assert_eq!(group.name, "Staging");
+        assert_eq!(group.defaults.env["AWS_PROFILE"], "staging");
+        assert_eq!(group.entries.len(), 1);
+
+        // Round-trips through the object form
+        let value = serde_json::to_value(&group).unwrap();
+        assert_eq!(value["group"], "Staging");
+    }
+
+    #[test]
+    fn test_group_named_group() {
+        let json = r#"{"group": [{"name": "Test", "cmd": "echo"}]}"#;
+        let Entry::Group(group) = serde_json::from_str(json).unwrap() else {
+            panic!("Expected group");
+        };
+        assert_eq!(group.name, "group");
+        assert!(group.defaults.is_empty());
+    }
+
+    #[test]
+    fn test_defaults_nested_take_precedence() {
+        let outer = Defaults {
+            cwd: Some("~/src".to_string()),
+            env: BTreeMap::from([
+                ("AWS_PROFILE".to_string(), "prod".to_string()),
+                ("REGION".to_string(), "eu".to_string()),
+            ]),
+            env_file: Some("outer.env".to_string()),
+            ..Defaults::default()
+        };
+        let inner = Defaults {
+            cwd: Some("~/src/infra".to_string()),
+            env: BTreeMap::from([("AWS_PROFILE".to_string(), "prod-admin".to_string())]),
+            env_file: None,
+            ..Defaults::default()
+        };
+
+        let mut action = Action {
+            env: BTreeMap::from([("REGION".to_string(), "us".to_string())]),
+            ..Default::default()
+        };
+        action.inherit(&inner.within(&outer));
+
+        assert_eq!(action.env["AWS_PROFILE"], "prod-admin");
+        assert_eq!(action.env["REGION"], "us");
+        assert_eq!(action.env_file.as_deref(), Some("outer.env"));
+        assert_eq!(action.cwd.as_deref(), Some("~/src/infra"));
+    }
+
+    #[test]
+    fn test_environment_env_overrides_file() {
+        let dir = std::env::temp_dir().join(format!("xshuttle-env-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(".env"),
+            "KUBECONFIG=/kube/staging\nAWS_PROFILE=staging\n",
+        )
+        .unwrap();
+
+        let action = Action {
+            cwd: Some(dir.to_string_lossy().into_owned()),
+            env: BTreeMap::from([("AWS_PROFILE".to_string(), "admin".to_string())]),
+            env_file: Some(".env".to_string()),
+            ..Default::default()
+        };
+        let env = action.environment().unwrap();
+        assert_eq!(env["KUBECONFIG"], "/kube/staging");
+        assert_eq!(env["AWS_PROFILE"], "admin");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_environment_missing_file() {
+        let action = Action {
+            env_file: Some("/nonexistent/xshuttle.env".to_string()),
+            ..Default::default()
+        };
+        let err = action.environment().unwrap_err();
+        assert!(
+            err.to_string().contains("/nonexistent/xshuttle.env"),
+            "{err}"
+        );
+    }
 }
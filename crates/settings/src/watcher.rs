@@ -0,0 +1,205 @@
+//! Change detection for config files.
+//!
+//! This module provides a polling [`Watcher`] that reports when any of a set
+//! of files has changed and then stayed unchanged for a debounce window.
+//! Polling metadata handles editor save patterns (atomic rename,
+//! truncate-then-write) uniformly and works for files that don't exist yet.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Snapshot of the metadata used to detect a change to a single file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Polling watcher for a fixed set of files.
+///
+/// Call [`Watcher::poll()`] regularly from the event loop. It returns `true`
+/// once per burst of changes, after the files have been stable for the
+/// debounce window.
+#[derive(Debug, Default)]
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    snapshot: Vec<Option<Fingerprint>>,
+    last_poll: Option<Instant>,
+    pending_since: Option<Instant>,
+}
+
+impl Watcher {
+    /// Minimum time between two metadata checks.
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+    /// Time the files must stay unchanged before a change is reported.
+    pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    /// Creates a watcher for the given paths, taking the initial snapshot.
+    ///
+    /// Paths that don't exist yet are watched for creation.
+    #[must_use]
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let snapshot = paths
+            .iter()
+            .map(PathBuf::as_path)
+            .map(Fingerprint::of)
+            .collect();
+        Self {
+            paths,
+            snapshot,
+            last_poll: None,
+            pending_since: None,
+        }
+    }
+
+    /// Returns the watched paths.
+    #[must_use]
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Checks the watched files for changes.
+    ///
+    /// Returns `true` when a change has settled and settings should be
+    /// reloaded.
+    pub fn poll(&mut self) -> bool {
+        self.poll_at(Instant::now())
+    }
+
+    fn poll_at(&mut self, now: Instant) -> bool {
+        if self
+            .last_poll
+            .is_some_and(|last| now.duration_since(last) < Self::POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.last_poll = Some(now);
+
+        let current: Vec<_> = self
+            .paths
+            .iter()
+            .map(PathBuf::as_path)
+            .map(Fingerprint::of)
+            .collect();
+        if current != self.snapshot {
+            self.snapshot = current;
+            self.pending_since = Some(now);
+            return false;
+        }
+
+        match self.pending_since {
+            Some(since) if now.duration_since(since) >= Self::DEBOUNCE => {
+                self.pending_since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("xshuttle-watcher-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_unchanged_file_not_reported() {
+        let path = temp_path("unchanged");
+        fs::write(&path, "{}").unwrap();
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        let start = Instant::now();
+        assert!(!watcher.poll_at(start));
+        assert!(!watcher.poll_at(start + Duration::from_secs(5)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_change_reported_after_debounce() {
+        let path = temp_path("changed");
+        fs::write(&path, "{}").unwrap();
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        fs::write(&path, r#"{"terminal": "kitty"}"#).unwrap();
+
+        let start = Instant::now();
+        // Change detected but still within the debounce window
+        assert!(!watcher.poll_at(start));
+        assert!(!watcher.poll_at(start + Duration::from_millis(300)));
+        // Stable for long enough
+        assert!(watcher.poll_at(start + Duration::from_secs(1)));
+        // Reported only once
+        assert!(!watcher.poll_at(start + Duration::from_secs(2)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_truncate_then_write_reported_once() {
+        let path = temp_path("truncate");
+        fs::write(&path, "{}").unwrap();
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        let start = Instant::now();
+
+        fs::write(&path, "").unwrap();
+        assert!(!watcher.poll_at(start));
+
+        fs::write(&path, r#"{"editor": "vim"}"#).unwrap();
+        assert!(!watcher.poll_at(start + Duration::from_millis(300)));
+
+        // Debounce restarts from the last observed change
+        assert!(!watcher.poll_at(start + Duration::from_millis(600)));
+        assert!(watcher.poll_at(start + Duration::from_millis(900)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_created_file_reported() {
+        let path = temp_path("created");
+        let _ = fs::remove_file(&path);
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        fs::write(&path, "{}").unwrap();
+
+        let start = Instant::now();
+        assert!(!watcher.poll_at(start));
+        assert!(watcher.poll_at(start + Duration::from_secs(1)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_poll_interval_throttles_checks() {
+        let path = temp_path("throttle");
+        fs::write(&path, "{}").unwrap();
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        let start = Instant::now();
+        assert!(!watcher.poll_at(start));
+
+        fs::write(&path, "{ }").unwrap();
+        // Within the poll interval the change isn't even looked at
+        assert!(!watcher.poll_at(start + Duration::from_millis(10)));
+        assert!(!watcher.poll_at(start + Duration::from_millis(300)));
+        assert!(watcher.poll_at(start + Duration::from_millis(900)));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{LaunchOptions, script};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Placeholder for the shell command line to run.
+const CMD: &str = "{cmd}";
+/// Placeholder for the working directory.
+const CWD: &str = "{cwd}";
+/// Placeholder for the window title.
+const TITLE: &str = "{title}";
+
+/// Title used when a launch doesn't set one.
+const DEFAULT_TITLE: &str = "xshuttle";
+
+/// A terminal defined in the config, launched as a binary with an argument
+/// template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Custom {
+    /// Name the terminal is selected by.
+    pub name: String,
+    /// Binary to run, looked up in `PATH` unless it's a path.
+    pub bin: PathBuf,
+    /// Arguments, with `{cmd}` standing for the shell command line to run,
+    /// `{cwd}` for the working directory and `{title}` for the window title.
+    pub args: Vec<String>,
+}
+
+impl Custom {
+    pub(crate) fn is_available(&self) -> bool {
+        which::which(&self.bin).is_ok()
+    }
+
+    /// Launches the terminal with the given command.
+    ///
+    /// The working directory in `options` fills in `{cwd}`, or becomes a
+    /// `cd` prefix if the template has no `{cwd}`. Environment variables are
+    /// set on the terminal process, which passes them on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the working directory doesn't exist or if
+    /// spawning fails.
+    pub fn launch(&self, command: &str, options: &LaunchOptions) -> Result<(), String> {
+        options.check()?;
+
+        let mut process = Command::new(&self.bin);
+        process
+            .args(self.build_args(command, options))
+            .envs(&options.env);
+        if let Some(cwd) = &options.cwd {
+            process.current_dir(cwd);
+        }
+        process
+            .spawn()
+            .map_err(|e| format!("Failed to launch {}: {e}", self.name))?;
+
+        Ok(())
+    }
+
+    fn build_args(&self, command: &str, options: &LaunchOptions) -> Vec<String> {
+        let has_cwd = self.args.iter().any(|a| a.contains(CWD));
+        let command = if has_cwd {
+            script(command, None, options)
+        } else {
+            script(command, options.cwd.as_deref(), options)
+        };
+
+        // Terminals start in the home directory unless told otherwise
+        let cwd = options
+            .cwd
+            .clone()
+            .or_else(|| std::env::var_os("HOME").map(PathBuf::from))
+            .unwrap_or_default();
+        let cwd = cwd.to_string_lossy();
+        let title = options.title.as_deref().unwrap_or(DEFAULT_TITLE);
+
+        let values = [(CWD, cwd.as_ref()), (TITLE, title), (CMD, command.as_str())];
+        self.args.iter().map(|a| substitute(a, &values)).collect()
+    }
+}
+
+/// Replaces the placeholders in `arg` in a single pass, so placeholders
+/// inside the inserted values are left as they are.
+fn substitute(arg: &str, values: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(arg.len());
+    let mut rest = arg;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some((key, value)) = values.iter().find(|(key, _)| rest.starts_with(key)) {
+            result.push_str(value);
+            rest = &rest[key.len()..];
+        } else {
+            result.push('{');
+            rest = &rest[1..];
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn foot() -> Custom {
+        Custom {
+            name: "foot".to_string(),
+            bin: PathBuf::from("foot"),
+            args: [
+                "--title={title}",
+                "--working-directory={cwd}",
+                "sh",
+                "-c",
+                "{cmd}",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let options = LaunchOptions {
+            cwd: Some(PathBuf::from("/src/infra")),
+            title: Some("Deploy".to_string()),
+            ..LaunchOptions::default()
+        };
+        assert_eq!(
+            foot().build_args("make", &options),
+            vec![
+                "--title=Deploy",
+                "--working-directory=/src/infra",
+                "sh",
+                "-c",
+                "eval make\nexec \"${SHELL:-sh}\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cwd_prefix_without_placeholder() {
+        let st = Custom {
+            name: "st".to_string(),
+            bin: PathBuf::from("st"),
+            args: ["-e", "sh", "-c", "{cmd}"].map(String::from).to_vec(),
+        };
+        let options = LaunchOptions {
+            cwd: Some(PathBuf::from("/src/my infra")),
+            ..LaunchOptions::default()
+        };
+        assert_eq!(
+            st.build_args("make", &options),
+            vec![
+                "-e",
+                "sh",
+                "-c",
+                "cd '/src/my infra' && eval make\nexec \"${SHELL:-sh}\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_awkward_commands_run_unchanged() {
+        for command in testing::AWKWARD {
+            let args = foot().build_args(command, &LaunchOptions::default());
+            assert_eq!(
+                testing::run(&args[2..]),
+                testing::expected(command),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_placeholders_in_values_are_kept() {
+        let options = LaunchOptions {
+            cwd: Some(PathBuf::from("/src/{title}")),
+            title: Some("echo {cmd} {cwd}".to_string()),
+            ..LaunchOptions::default()
+        };
+        let args = foot().build_args("echo '{title}'", &options);
+        assert_eq!(args[0], "--title=echo {cmd} {cwd}");
+        assert_eq!(args[1], "--working-directory=/src/{title}");
+        assert_eq!(testing::run(&args[2..]), "{title}\n");
+    }
+
+    #[test]
+    fn test_default_title() {
+        let args = foot().build_args("htop", &LaunchOptions::default());
+        assert_eq!(args[0], "--title=xshuttle");
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{LaunchOptions, shell_command};
+use std::fs::File;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// A command running in the background, without a terminal.
+///
+/// The command runs in a process group of its own, so it isn't tied to
+/// xshuttle's, and its output goes to a log file.
+#[derive(Debug)]
+pub struct Job {
+    child: Child,
+    log: PathBuf,
+    started: Instant,
+    /// When the job was asked to stop, if it was.
+    stopping: Option<Instant>,
+    /// When the job's process group was sent `SIGKILL`, if it was.
+    killed: Option<Instant>,
+    /// When the job's process group was last checked on, and whether any
+    /// process was left in it then.
+    group_checked: Option<(Instant, bool)>,
+}
+
+impl Job {
+    /// Starts the given command, writing its stdout and stderr to the file
+    /// at `log`, which is truncated first.
+    ///
+    /// The working directory, environment variables and shell in `options`
+    /// apply as in a terminal; the rest is about terminals and is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the working directory doesn't exist, if the log
+    /// file can't be created, or if spawning fails.
+    pub fn spawn(command: &str, options: &LaunchOptions, log: PathBuf) -> Result<Self, String> {
+        options.check()?;
+
+        let stdout = File::create(&log)
+            .map_err(|e| format!("Failed to create log file {}: {e}", log.display()))?;
+        let stderr = stdout
+            .try_clone()
+            .map_err(|e| format!("Failed to create log file {}: {e}", log.display()))?;
+
+        let mut process = Command::new("sh");
+        process
+            .arg("-c")
+            .arg(shell_command(command, None, &options.shell))
+            .envs(&options.env)
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
+            .process_group(0);
+        if let Some(cwd) = &options.cwd {
+            process.current_dir(cwd);
+        }
+        let child = process
+            .spawn()
+            .map_err(|e| format!("Failed to start command: {e}"))?;
+
+        Ok(Self {
+            child,
+            log,
+            started: Instant::now(),
+            stopping: None,
+            killed: None,
+            group_checked: None,
+        })
+    }
+
+    /// Returns the process ID, which is also the ID of the job's process
+    /// group.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns the file the job's output goes to.
+    pub fn log(&self) -> &Path {
+        &self.log
+    }
+
+    /// Returns how long the job has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Returns `true` if the job has been asked to stop.
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.is_some()
+    }
+
+    /// Asks the job to stop by sending `SIGTERM` to its process group.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal can't be sent.
+    pub fn stop(&mut self) -> Result<(), String> {
+        self.signal("TERM")?;
+        self.stopping.get_or_insert_with(Instant::now);
+        Ok(())
+    }
+
+    /// Sends `SIGKILL` to the job's process group if it was asked to stop
+    /// more than `timeout` ago and hasn't yet. The signal is only sent once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal can't be sent.
+    pub fn kill_if_overdue(&mut self, timeout: Duration) -> Result<(), String> {
+        match self.stopping {
+            Some(stopping) if self.killed.is_none() && stopping.elapsed() >= timeout => {
+                self.killed = Some(Instant::now());
+                self.signal("KILL")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `true` while any process in the job's process group is left,
+    /// which the processes the command started can be after it finished.
+    pub fn is_group_running(&self) -> bool {
+        let group = format!("-{}", self.id());
+        Command::new("kill")
+            .args(["-0", "--", &group])
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Returns `true` while any process in the job's process group is left,
+    /// as [`Job::is_group_running()`] does, but checks at most once per
+    /// `interval` and returns the last answer in between.
+    pub fn poll_group_running(&mut self, interval: Duration) -> bool {
+        match self.group_checked {
+            Some((checked, running)) if checked.elapsed() < interval => running,
+            _ => {
+                let running = self.is_group_running();
+                self.group_checked = Some((Instant::now(), running));
+                running
+            }
+        }
+    }
+
+    /// Returns how long ago the job's process group was sent `SIGKILL`, if
+    /// it was.
+    pub fn killed_for(&self) -> Option<Duration> {
+        self.killed.map(|killed| killed.elapsed())
+    }
+
+    /// Sends a signal to every process in the job's process group.
+    fn signal(&self, signal: &str) -> Result<(), String> {
+        let group = format!("-{}", self.id());
+        let status = Command::new("kill")
+            .args(["-s", signal, "--", &group])
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| format!("Failed to run kill: {e}"))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Failed to send SIG{signal} to process group {}",
+                self.id()
+            ))
+        }
+    }
+
+    /// Returns the exit status if the job has finished, without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the job's status can't be determined.
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>, String> {
+        self.child
+            .try_wait()
+            .map_err(|e| format!("Failed to check on command: {e}"))
+    }
+
+    /// Stops keeping track of the job, leaving a thread to reap the command
+    /// once it exits, so it doesn't linger as a zombie.
+    pub fn detach(mut self) {
+        std::thread::spawn(move || self.child.wait());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeepOpen, Shell};
+    use std::os::unix::process::ExitStatusExt;
+
+    fn wait(job: &mut Job) -> ExitStatus {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = job.try_wait().unwrap() {
+                return status;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5), "job hangs");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_output_goes_to_log() {
+        let dir = std::env::temp_dir();
+        let log = dir.join(format!("xshuttle-test-job-{}.log", std::process::id()));
+        let options = LaunchOptions {
+            cwd: Some(dir.clone()),
+            env: [("GREETING".to_string(), "it's $HOME".to_string())].into(),
+            // Means nothing without a terminal
+            keep_open: KeepOpen::Pause,
+            shell: Shell {
+                program: None,
+                login: false,
+            },
+            ..LaunchOptions::default()
+        };
+
+        let mut job = Job::spawn(
+            "echo \"$GREETING\"; pwd; echo oops >&2; exit 3",
+            &options,
+            log.clone(),
+        )
+        .unwrap();
+        let status = wait(&mut job);
+        let output = std::fs::read_to_string(job.log()).unwrap();
+        let _ = std::fs::remove_file(&log);
+
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(
+            output,
+            format!(
+                "it's $HOME\n{}\noops\n",
+                dir.canonicalize().unwrap().display()
+            )
+        );
+    }
+
+    fn spawn(command: &str) -> Job {
+        let log = std::env::temp_dir().join(format!(
+            "xshuttle-test-job-{}-{}.log",
+            std::process::id(),
+            command.len()
+        ));
+        Job::spawn(command, &LaunchOptions::default(), log).unwrap()
+    }
+
+    #[test]
+    fn test_stop() {
+        let mut job = spawn("sleep 30");
+        job.stop().unwrap();
+        assert!(job.is_stopping());
+        job.kill_if_overdue(Duration::from_secs(30)).unwrap();
+
+        let status = wait(&mut job);
+        let _ = std::fs::remove_file(job.log());
+        assert_eq!(status.signal(), Some(15));
+    }
+
+    #[test]
+    fn test_kill_if_overdue() {
+        let mut job = spawn("trap '' TERM; sleep 30; echo ignored");
+        // Give the trap time to be set
+        std::thread::sleep(Duration::from_millis(100));
+        job.stop().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(job.try_wait().unwrap().is_none());
+
+        job.kill_if_overdue(Duration::ZERO).unwrap();
+        let status = wait(&mut job);
+        let _ = std::fs::remove_file(job.log());
+        assert_eq!(status.signal(), Some(9));
+    }
+
+    #[test]
+    fn test_group_outlives_stopped_command() {
+        // The command dies on SIGTERM, but the process it started ignores it
+        let mut job = spawn("sh -c \"trap '' TERM; exec sleep 30\" & wait");
+        std::thread::sleep(Duration::from_millis(100));
+        job.stop().unwrap();
+
+        let status = wait(&mut job);
+        assert_eq!(status.signal(), Some(15));
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(job.is_group_running());
+
+        job.kill_if_overdue(Duration::ZERO).unwrap();
+        assert!(job.killed_for().is_some());
+        // Isn't sent again, which would fail once the group is gone
+        let started = Instant::now();
+        while job.is_group_running() {
+            assert!(started.elapsed() < Duration::from_secs(5), "group hangs");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        job.kill_if_overdue(Duration::ZERO).unwrap();
+        let _ = std::fs::remove_file(job.log());
+    }
+
+    #[test]
+    fn test_poll_group_running() {
+        let mut job = spawn("sleep 30");
+        assert!(job.poll_group_running(Duration::from_secs(30)));
+        job.stop().unwrap();
+        wait(&mut job);
+        let _ = std::fs::remove_file(job.log());
+
+        // The last answer stands until the interval has passed
+        assert!(job.poll_group_running(Duration::from_secs(30)));
+        let started = Instant::now();
+        while job.poll_group_running(Duration::ZERO) {
+            assert!(started.elapsed() < Duration::from_secs(5), "group hangs");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_missing_cwd_rejected() {
+        let options = LaunchOptions {
+            cwd: Some(PathBuf::from("/nonexistent/xshuttle")),
+            ..LaunchOptions::default()
+        };
+        let log = std::env::temp_dir().join("xshuttle-test-never-written.log");
+        assert!(Job::spawn("true", &options, log).is_err());
+    }
+}
@@ -1,9 +1,688 @@
+mod custom;
+mod job;
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
+mod tmux;
 
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+pub use custom::Custom;
+pub use job::Job;
 #[cfg(target_os = "linux")]
 pub use linux::Terminal;
 #[cfg(target_os = "macos")]
 pub use macos::Terminal;
+pub use tmux::{DEFAULT_SESSION, Tmux};
+
+/// How to run a command, besides the command itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// Directory to start the command in, instead of the terminal's default.
+    pub cwd: Option<PathBuf>,
+    /// Environment variables to set for the command.
+    pub env: BTreeMap<String, String>,
+    /// Window title, for terminals that can be given one.
+    pub title: Option<String>,
+    /// What to do once the command has finished.
+    pub keep_open: KeepOpen,
+    /// Shell to run the command in.
+    pub shell: Shell,
+    /// Whether to open a new window or a tab.
+    pub open_in: OpenIn,
+}
+
+/// Where a command opens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenIn {
+    /// A new terminal window.
+    #[default]
+    Window,
+    /// A new tab in a running terminal, for terminals that support it. Falls
+    /// back to a window if there's no running terminal to open it in.
+    Tab,
+}
+
+/// The shell a command runs in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shell {
+    /// Shell program, looked up in `PATH` unless it's a path. `None` runs
+    /// the command in the `sh` that runs the launch script.
+    pub program: Option<PathBuf>,
+    /// Whether to start the shell as a login and interactive shell, so it
+    /// reads the user's profile and rc files.
+    pub login: bool,
+}
+
+impl Default for Shell {
+    /// Returns the platform's default: plain `sh` on Linux, and a login
+    /// `zsh`, as macOS terminals start, on macOS.
+    fn default() -> Self {
+        let macos = cfg!(target_os = "macos");
+        Self {
+            program: macos.then(|| PathBuf::from("/bin/zsh")),
+            login: macos,
+        }
+    }
+}
+
+impl Shell {
+    /// Returns the arguments that run a command given after them, or `None`
+    /// if the command is run by the launch script itself.
+    fn argv(&self) -> Option<Vec<String>> {
+        if self.program.is_none() && !self.login {
+            return None;
+        }
+
+        let program = self
+            .program
+            .as_ref()
+            .map_or_else(|| "sh".to_string(), |p| p.to_string_lossy().into_owned());
+        let mut argv = vec![program];
+        if self.login {
+            argv.extend(["-l".to_string(), "-i".to_string()]);
+        }
+        argv.push("-c".to_string());
+        Some(argv)
+    }
+}
+
+/// What happens in the terminal once the command has finished.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeepOpen {
+    /// Start the user's shell from `$SHELL`.
+    #[default]
+    Shell,
+    /// Close the terminal.
+    Close,
+    /// Close the terminal if the command succeeded, and pause otherwise.
+    CloseOnSuccess,
+    /// Show the exit code and wait for a key press before closing.
+    Pause,
+}
+
+/// Shell code that shows the exit code in `$code` and waits for a key.
+const PAUSE: &str = concat!(
+    "printf '\\n[Exited with code %s. Press any key to close.]' \"$code\"\n",
+    "stty -icanon -echo 2>/dev/null\n",
+    "dd bs=1 count=1 >/dev/null 2>&1",
+);
+
+impl KeepOpen {
+    /// Returns the shell code to run after the command.
+    fn epilogue(self) -> String {
+        match self {
+            Self::Shell => "exec \"${SHELL:-sh}\"".to_string(),
+            Self::Close => String::new(),
+            Self::CloseOnSuccess => format!("code=$?\n[ \"$code\" -eq 0 ] && exit\n{PAUSE}"),
+            Self::Pause => format!("code=$?\n{PAUSE}"),
+        }
+    }
+}
+
+impl LaunchOptions {
+    /// Checks that the options can be applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the working directory doesn't exist.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        match &self.cwd {
+            Some(cwd) if !cwd.is_dir() => Err(format!(
+                "Working directory {} does not exist",
+                cwd.display()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The terminal name that stands for auto-detection.
+const DEFAULT: &str = "default";
+/// The terminal name that opens commands in a tmux session.
+const TMUX: &str = "tmux";
+
+/// A terminal to launch commands in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Launcher {
+    /// A built-in terminal, or auto-detection among the built-in terminals
+    /// for [`Terminal::Default`].
+    BuiltIn(Terminal),
+    /// A terminal defined in the config.
+    Custom(Custom),
+    /// A window in a tmux session.
+    Tmux(Tmux),
+}
+
+impl Launcher {
+    /// Launches the terminal with the given command.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no terminal is found, if the working directory
+    /// doesn't exist, or if the terminal fails to launch.
+    pub fn launch(&self, command: &str, options: &LaunchOptions) -> Result<(), String> {
+        match self {
+            Self::BuiltIn(terminal) => terminal.launch(command, options),
+            Self::Custom(custom) => custom.launch(command, options),
+            Self::Tmux(tmux) => tmux.launch(command, options),
+        }
+    }
+
+    /// Launches several commands at once, named `title` together: as tiled
+    /// panes in one window for tmux, synchronized if `synchronize` is set,
+    /// and in tabs where the terminal supports them otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if launching any of the commands fails, after the
+    /// others have been launched.
+    pub fn launch_all(
+        &self,
+        title: &str,
+        commands: &[(String, LaunchOptions)],
+        synchronize: bool,
+    ) -> Result<(), String> {
+        let tabs: Vec<(String, LaunchOptions)> = commands
+            .iter()
+            .map(|(command, options)| {
+                let options = LaunchOptions {
+                    open_in: OpenIn::Tab,
+                    ..options.clone()
+                };
+                (command.clone(), options)
+            })
+            .collect();
+
+        match self {
+            Self::Tmux(tmux) => tmux.launch_tiled(title, commands, synchronize),
+            Self::BuiltIn(terminal) => terminal.launch_each(&tabs),
+            Self::Custom(_) => join_errors(
+                tabs.iter()
+                    .filter_map(|(command, options)| self.launch(command, options).err()),
+            ),
+        }
+    }
+}
+
+/// The terminals available for launching: the built-in ones plus those
+/// defined in the config, and `tmux`.
+///
+/// A custom terminal named like a built-in one replaces it.
+#[derive(Debug, Clone)]
+pub struct Terminals {
+    custom: Vec<Custom>,
+    session: String,
+    socket: Option<String>,
+}
+
+impl Default for Terminals {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Terminals {
+    /// Creates a set of terminals with the given custom ones, which are
+    /// preferred over the built-in ones during auto-detection.
+    pub fn new(custom: Vec<Custom>) -> Self {
+        Self {
+            custom,
+            session: DEFAULT_SESSION.to_string(),
+            socket: None,
+        }
+    }
+
+    /// Sets the session that `tmux` opens commands in, and the socket name
+    /// of its server.
+    #[must_use]
+    pub fn with_tmux(mut self, session: String, socket: Option<String>) -> Self {
+        self.session = session;
+        self.socket = socket;
+        self
+    }
+
+    /// Returns the names accepted in the `terminal` setting.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        // Shorten the built-in names' lifetime to match the custom ones
+        let builtin = Terminal::names().map(|name| -> &str { name });
+        let tmux = (!self.is_custom(TMUX)).then_some(TMUX);
+        self.custom
+            .iter()
+            .map(|c| c.name.as_str())
+            .chain(builtin)
+            .chain(tmux)
+    }
+
+    /// Returns `true` if `name` names a known terminal or `default`.
+    pub fn is_known(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case(DEFAULT) || name == TMUX || self.find(name).is_some()
+    }
+
+    fn is_custom(&self, name: &str) -> bool {
+        self.custom.iter().any(|c| c.name == name)
+    }
+
+    fn find(&self, name: &str) -> Option<Launcher> {
+        let custom = self.custom.iter().find(|c| c.name == name);
+        match custom {
+            Some(custom) => Some(Launcher::Custom(custom.clone())),
+            None => match Terminal::from(name) {
+                Terminal::Default => None,
+                terminal => Some(Launcher::BuiltIn(terminal)),
+            },
+        }
+    }
+
+    fn is_available(launcher: &Launcher) -> bool {
+        match launcher {
+            Launcher::BuiltIn(terminal) => terminal.is_available(),
+            Launcher::Custom(custom) => custom.is_available(),
+            Launcher::Tmux(_) => Tmux::is_available(),
+        }
+    }
+
+    /// Auto-detects a terminal: the first installed custom terminal, or
+    /// else the built-in detection.
+    fn detect(&self) -> Launcher {
+        self.custom
+            .iter()
+            .find(|c| c.is_available())
+            .map_or(Launcher::BuiltIn(Terminal::Default), |c| {
+                Launcher::Custom(c.clone())
+            })
+    }
+
+    /// Picks the first installed terminal from a list of names, in order.
+    ///
+    /// The name `default` stands for auto-detection and ends the search, as
+    /// does the end of the list. `tmux` is attached to in the terminal that
+    /// the rest of the list picks. Returns the terminal to launch along with
+    /// a warning for every unknown or missing terminal that was skipped.
+    pub fn choose<S: AsRef<str>>(&self, names: &[S]) -> (Launcher, Vec<String>) {
+        let mut warnings = Vec::new();
+
+        for (i, name) in names.iter().map(AsRef::as_ref).enumerate() {
+            if name.eq_ignore_ascii_case(DEFAULT) {
+                return (self.detect(), warnings);
+            }
+            if name == TMUX && !self.is_custom(TMUX) {
+                if !Tmux::is_available() {
+                    warnings.push(format!("Terminal '{name}' is not installed"));
+                    continue;
+                }
+                let (attach, more) = self.choose(&names[i + 1..]);
+                warnings.extend(more);
+                let tmux = Tmux {
+                    session: self.session.clone(),
+                    socket: self.socket.clone(),
+                    attach: Box::new(attach),
+                };
+                return (Launcher::Tmux(tmux), warnings);
+            }
+            match self.find(name) {
+                None => warnings.push(format!("Unknown terminal '{name}'")),
+                Some(launcher) if Self::is_available(&launcher) => return (launcher, warnings),
+                Some(_) => warnings.push(format!("Terminal '{name}' is not installed")),
+            }
+        }
+
+        if !warnings.is_empty() {
+            warnings.push("No configured terminal is available, using auto-detection".to_string());
+        }
+        (self.detect(), warnings)
+    }
+
+    /// Picks a terminal for each item from its own list of names, as
+    /// [`Terminals::choose()`] does, and groups the items that pick the same
+    /// one, in the order the terminals are first picked. Each warning is
+    /// returned once.
+    pub fn choose_each<N, S, T>(
+        &self,
+        items: impl IntoIterator<Item = (N, T)>,
+    ) -> (Vec<(Launcher, Vec<T>)>, Vec<String>)
+    where
+        N: AsRef<[S]>,
+        S: AsRef<str>,
+    {
+        let mut groups: Vec<(Launcher, Vec<T>)> = Vec::new();
+        let mut warnings = Vec::new();
+
+        for (names, item) in items {
+            let (launcher, more) = self.choose(names.as_ref());
+            for warning in more {
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+            match groups.iter_mut().find(|(picked, _)| *picked == launcher) {
+                Some((_, items)) => items.push(item),
+                None => groups.push((launcher, vec![item])),
+            }
+        }
+
+        (groups, warnings)
+    }
+}
+
+/// Returns a shell command line that runs `command` as written in `shell`,
+/// after a `cd` into `cwd` for terminals that can't be told the working
+/// directory.
+///
+/// The command is quoted and run with `eval` or passed to the shell as an
+/// argument, so quotes, `$`, backticks and newlines in it reach the shell
+/// unchanged, whatever the line ends up in.
+pub(crate) fn shell_command(command: &str, cwd: Option<&Path>, shell: &Shell) -> String {
+    let command = match shell.argv() {
+        Some(mut argv) => {
+            argv.push(command.to_string());
+            shell_words::join(argv)
+        }
+        None => format!("eval {}", shell_words::quote(command)),
+    };
+    match cwd {
+        Some(cwd) => format!(
+            "cd {} && {command}",
+            shell_words::quote(&cwd.to_string_lossy())
+        ),
+        None => command,
+    }
+}
+
+/// Returns the errors joined into one, one per line, or `Ok` if there are
+/// none.
+pub(crate) fn join_errors(errors: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let errors: Vec<String> = errors.into_iter().collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Returns `export` lines for the given environment variables.
+pub(crate) fn exports(env: &BTreeMap<String, String>) -> String {
+    env.iter().fold(String::new(), |mut lines, (key, value)| {
+        let _ = writeln!(lines, "export {key}={}", shell_words::quote(value));
+        lines
+    })
+}
+
+/// Returns a shell script that runs `command` as [`shell_command()`] does,
+/// then does what the options ask for once it has finished.
+///
+/// `cwd` is taken instead of the options' own working directory, for
+/// terminals that were already told it.
+pub(crate) fn script(command: &str, cwd: Option<&Path>, options: &LaunchOptions) -> String {
+    let keep_open = options.keep_open;
+    let command = shell_command(command, cwd, &options.shell);
+    match keep_open {
+        KeepOpen::Close => command,
+        KeepOpen::Shell => format!("{command}\n{}", keep_open.epilogue()),
+        // In a subshell, so an `exit` in the command can't skip the prompt
+        KeepOpen::CloseOnSuccess | KeepOpen::Pause => {
+            format!("({command})\n{}", keep_open.epilogue())
+        }
+    }
+}
+
+/// Helpers for checking that commands survive the trip through a terminal's
+/// arguments.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::process::{Command, Stdio};
+
+    /// Commands that break naive quoting.
+    pub(crate) const AWKWARD: &[&str] = &[
+        "echo 'single quoted'",
+        r#"echo "double \"quoted\"""#,
+        "echo \"it's\"",
+        "echo $((1 + 2)) '$HOME' \\$PATH",
+        "echo `echo backticks` $(echo subshell)",
+        "echo first\necho second",
+        "echo héllo wörld ✓ 日本",
+        "echo a; echo b && echo c | cat",
+        "echo trailing # comment",
+        "x='; exec false'; echo \"$x\"",
+    ];
+
+    /// Runs `argv` without input and returns what it printed.
+    pub(crate) fn run(argv: &[String]) -> String {
+        let output = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::null())
+            .output()
+            .expect("failed to run shell");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Returns what `command` prints when run by the shell directly.
+    pub(crate) fn expected(command: &str) -> String {
+        run(&["sh", "-c", command].map(String::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTO: Launcher = Launcher::BuiltIn(Terminal::Default);
+
+    fn custom(name: &str, bin: &str) -> Custom {
+        Custom {
+            name: name.to_string(),
+            bin: PathBuf::from(bin),
+            args: vec!["-e".to_string(), "{cmd}".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_shell_command_runs_awkward_commands() {
+        for command in testing::AWKWARD {
+            let line = shell_command(command, Some(Path::new("/")), &SH);
+            assert_eq!(
+                testing::run(&["sh", "-c", &line].map(String::from)),
+                testing::expected(command),
+                "{command}"
+            );
+        }
+    }
+
+    /// Runs commands in the launch script's own `sh`.
+    const SH: Shell = Shell {
+        program: None,
+        login: false,
+    };
+
+    fn keeping(keep_open: KeepOpen) -> LaunchOptions {
+        LaunchOptions {
+            keep_open,
+            shell: SH,
+            ..LaunchOptions::default()
+        }
+    }
+
+    fn run_script(command: &str, keep_open: KeepOpen) -> String {
+        let script = script(command, None, &keeping(keep_open));
+        testing::run(&["sh", "-c", &script].map(String::from))
+    }
+
+    #[test]
+    fn test_shell_command_in_other_shell() {
+        let bash = Shell {
+            program: Some(PathBuf::from("bash")),
+            login: false,
+        };
+        for command in testing::AWKWARD {
+            let line = shell_command(command, None, &bash);
+            assert_eq!(
+                testing::run(&["sh", "-c", &line].map(String::from)),
+                testing::expected(command),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_login_shell() {
+        let login = Shell {
+            program: Some(PathBuf::from("/bin/zsh")),
+            login: true,
+        };
+        assert_eq!(
+            shell_command("echo $PATH", None, &login),
+            "/bin/zsh -l -i -c 'echo $PATH'"
+        );
+
+        let login_sh = Shell { login: true, ..SH };
+        assert_eq!(shell_command("htop", None, &login_sh), "sh -l -i -c htop");
+    }
+
+    #[test]
+    fn test_keep_open_shell() {
+        assert_eq!(
+            script("htop", None, &keeping(KeepOpen::Shell)),
+            "eval htop\nexec \"${SHELL:-sh}\""
+        );
+    }
+
+    #[test]
+    fn test_keep_open_close() {
+        assert_eq!(script("htop", None, &keeping(KeepOpen::Close)), "eval htop");
+        assert_eq!(run_script("echo done", KeepOpen::Close), "done\n");
+    }
+
+    #[test]
+    fn test_keep_open_pause_shows_exit_code() {
+        let output = run_script("echo done; exit 3", KeepOpen::Pause);
+        assert!(output.starts_with("done\n"), "{output}");
+        assert!(output.contains("Exited with code 3"), "{output}");
+    }
+
+    #[test]
+    fn test_keep_open_close_on_success() {
+        assert_eq!(run_script("echo done", KeepOpen::CloseOnSuccess), "done\n");
+
+        let output = run_script("false", KeepOpen::CloseOnSuccess);
+        assert!(output.contains("Exited with code 1"), "{output}");
+    }
+
+    #[test]
+    fn test_choose_nothing_configured() {
+        let (terminal, warnings) = Terminals::default().choose::<&str>(&[]);
+        assert_eq!(terminal, AUTO);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_choose_stops_at_default() {
+        let (terminal, warnings) =
+            Terminals::default().choose(&["no-such-term", "Default", "xterm"]);
+        assert_eq!(terminal, AUTO);
+        assert_eq!(warnings, vec!["Unknown terminal 'no-such-term'"]);
+    }
+
+    #[test]
+    fn test_choose_warns_when_falling_back() {
+        let (terminal, warnings) = Terminals::default().choose(&["no-such-term"]);
+        assert_eq!(terminal, AUTO);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[1].contains("auto-detection"));
+    }
+
+    #[test]
+    fn test_choose_custom() {
+        let wrapper = custom("wrapper", "sh");
+        let terminals = Terminals::new(vec![
+            custom("missing", "/nonexistent/term"),
+            wrapper.clone(),
+        ]);
+
+        let (terminal, warnings) = terminals.choose(&["missing", "wrapper"]);
+        assert_eq!(terminal, Launcher::Custom(wrapper));
+        assert_eq!(warnings, vec!["Terminal 'missing' is not installed"]);
+    }
+
+    #[test]
+    fn test_choose_tmux_attaches_in_rest() {
+        if !Tmux::is_available() {
+            return;
+        }
+        let wrapper = custom("wrapper", "sh");
+        let terminals = Terminals::new(vec![wrapper.clone()])
+            .with_tmux("work".to_string(), Some("alt".to_string()));
+
+        let (terminal, warnings) = terminals.choose(&["tmux", "no-such-term", "wrapper"]);
+        assert_eq!(
+            terminal,
+            Launcher::Tmux(Tmux {
+                session: "work".to_string(),
+                socket: Some("alt".to_string()),
+                attach: Box::new(Launcher::Custom(wrapper)),
+            })
+        );
+        assert_eq!(warnings, vec!["Unknown terminal 'no-such-term'"]);
+        assert!(terminals.is_known("tmux"));
+    }
+
+    #[test]
+    fn test_choose_each_groups_by_terminal() {
+        let (a, b) = (custom("a", "sh"), custom("b", "sh"));
+        let terminals = Terminals::new(vec![a.clone(), b.clone()]);
+
+        let (groups, warnings) = terminals.choose_each([
+            (vec!["a"], 1),
+            (vec!["missing", "b"], 2),
+            (vec!["a"], 3),
+            (vec!["missing", "b"], 4),
+        ]);
+        assert_eq!(
+            groups,
+            vec![
+                (Launcher::Custom(a), vec![1, 3]),
+                (Launcher::Custom(b), vec![2, 4])
+            ]
+        );
+        assert_eq!(warnings, vec!["Unknown terminal 'missing'"]);
+    }
+
+    #[test]
+    fn test_choose_each_group_default_tmux() {
+        if !Tmux::is_available() {
+            return;
+        }
+        let terminals = Terminals::new(vec![custom("wrapper", "sh")]);
+
+        // A group defaulting to tmux, with one action that picks its own
+        let group = ["tmux", "wrapper"];
+        let (groups, _) = terminals.choose_each([
+            (&group[..], "web"),
+            (&group[..], "db"),
+            (&["wrapper"][..], "top"),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert!(matches!(groups[0].0, Launcher::Tmux(_)));
+        assert_eq!(groups[0].1, ["web", "db"]);
+        assert_eq!(groups[1].1, ["top"]);
+    }
+
+    #[test]
+    fn test_detect_prefers_custom() {
+        let wrapper = custom("wrapper", "sh");
+        let terminals = Terminals::new(vec![wrapper.clone()]);
+        assert_eq!(terminals.choose(&["default"]).0, Launcher::Custom(wrapper));
+    }
+
+    #[test]
+    fn test_custom_names_are_known() {
+        let terminals = Terminals::new(vec![custom("foot", "foot")]);
+        assert!(terminals.is_known("foot"));
+        assert!(terminals.is_known("kitty"));
+        assert!(terminals.is_known("default"));
+        assert!(!terminals.is_known("no-such-term"));
+        assert_eq!(terminals.names().next(), Some("foot"));
+    }
+}
@@ -1,4 +1,10 @@
-use std::process::Command;
+use crate::{LaunchOptions, OpenIn, exports, join_errors, script};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long to wait for a running terminal to open a tab, as launches
+/// happen on the UI thread.
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Terminal {
@@ -35,22 +41,78 @@ impl Terminal {
         }
     }
 
-    fn args(self) -> &'static [&'static str] {
+    /// Arguments that introduce the command to run.
+    fn exec_args(self) -> &'static [&'static str] {
         match self {
-            Self::Default => &[],
-            Self::Gnome => &["--", "sh", "-c", "{}; exec bash"],
+            Self::Default | Self::Kitty => &[],
+            Self::Gnome | Self::Wezterm => &["--"],
             Self::Konsole
+            | Self::Xfce4
             | Self::Alacritty
             | Self::Ghostty
+            | Self::Tilix
+            | Self::Terminator
             | Self::XTerminalEmulator
-            | Self::Xterm => &["-e", "sh", "-c", "{}; exec bash"],
-            Self::Xfce4 | Self::Tilix | Self::Terminator => &["-e", "sh -c '{}; exec bash'"],
-            Self::Kitty => &["sh", "-c", "{}; exec bash"],
-            Self::Wezterm => &["start", "--", "sh", "-c", "{}; exec bash"],
+            | Self::Xterm => &["-e"],
+        }
+    }
+
+    /// Whether the terminal takes the command as a single string, which it
+    /// splits into arguments itself, rather than as separate arguments.
+    fn joins_command(self) -> bool {
+        matches!(self, Self::Xfce4 | Self::Tilix | Self::Terminator)
+    }
+
+    /// Arguments that go before all options, such as a subcommand.
+    fn subcommand(self) -> &'static [&'static str] {
+        match self {
+            Self::Wezterm => &["start"],
+            _ => &[],
         }
     }
 
-    fn is_available(self) -> bool {
+    /// Options that make the terminal open a tab in a running window, which
+    /// it falls back from by itself.
+    fn tab_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Gnome => &["--tab"],
+            _ => &[],
+        }
+    }
+
+    /// Remote control command that opens a tab in a running instance,
+    /// followed by the arguments that introduce the command to run.
+    ///
+    /// The tab is started by the running instance, so it doesn't inherit
+    /// the environment of the command that opens it.
+    ///
+    /// `kitty @` only reaches kitty from outside it through the socket in
+    /// `KITTY_LISTEN_ON`, which xshuttle has to be started with. `WezTerm`
+    /// is kept from starting a hidden instance when none is running.
+    fn remote_tab_args(self) -> Option<(&'static [&'static str], &'static [&'static str])> {
+        match self {
+            Self::Kitty => Some((&["@", "launch", "--type=tab"], &[])),
+            Self::Wezterm => Some((&["cli", "--no-auto-start", "spawn"], &["--"])),
+            _ => None,
+        }
+    }
+
+    /// Option setting the working directory, with `{}` standing for the
+    /// path. Empty if the terminal has none.
+    fn cwd_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Gnome | Self::Xfce4 | Self::Ghostty | Self::Tilix | Self::Terminator => {
+                &["--working-directory={}"]
+            }
+            Self::Alacritty => &["--working-directory", "{}"],
+            Self::Konsole => &["--workdir", "{}"],
+            Self::Kitty => &["--directory", "{}"],
+            Self::Wezterm => &["--cwd", "{}"],
+            Self::Default | Self::XTerminalEmulator | Self::Xterm => &[],
+        }
+    }
+
+    pub(crate) fn is_available(self) -> bool {
         self.bin().is_some_and(|bin| which::which(bin).is_ok())
     }
 
@@ -70,6 +132,11 @@ impl Terminal {
         ]
     }
 
+    /// Returns the names accepted in the `terminal` setting.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Self::all().iter().filter_map(|t| t.bin())
+    }
+
     fn detect(self) -> Option<Terminal> {
         // Prefer requested terminal if available
         if self != Self::Default && self.is_available() {
@@ -91,32 +158,182 @@ impl Terminal {
 
     /// Launches a terminal with the given command.
     ///
+    /// The working directory in `options` is passed with the terminal's own
+    /// option where it has one, and as a `cd` prefix otherwise. Environment
+    /// variables are set on the terminal process, which passes them on.
+    ///
+    /// To open a tab, kitty and `WezTerm` are asked through their remote
+    /// control, and gnome-terminal is given `--tab`. A window opens instead
+    /// if that fails or the terminal has no tabs. Asking the remote control
+    /// happens on a thread of its own, as a running instance can take a
+    /// while to answer.
+    ///
     /// # Errors
     ///
-    /// Returns an error if no terminal emulator is found or if spawning fails.
+    /// Returns an error if no terminal emulator is found, if the working
+    /// directory doesn't exist, or if spawning fails.
+    pub fn launch(&self, command: &str, options: &LaunchOptions) -> Result<(), String> {
+        options.check()?;
+        self.launch_each(&[(command.to_string(), options.clone())])
+    }
+
+    /// Launches several commands, each as [`Terminal::launch()`] does.
+    ///
+    /// Tabs opened through the remote control open in order, and once
+    /// asking it fails, the remaining ones open in windows without asking
+    /// again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no terminal emulator is found, or naming each
+    /// command whose working directory doesn't exist or that fails to
+    /// spawn, after the others have been launched.
     ///
     /// # Panics
     ///
     /// Panics if `detect()` returns a terminal variant that has no binary name.
     /// This should never happen in practice.
-    pub fn launch(&self, command: &str) -> Result<(), String> {
+    pub(crate) fn launch_each(self, commands: &[(String, LaunchOptions)]) -> Result<(), String> {
         let terminal = self
             .detect()
             .ok_or("No terminal found. Install gnome-terminal, konsole, alacritty, or xterm.")?;
-
         let bin = terminal.bin().expect("detect() never returns Default");
-        let args: Vec<String> = terminal
-            .args()
-            .iter()
-            .map(|a| a.replace("{}", command))
-            .collect();
 
-        Command::new(bin)
-            .args(&args)
-            .spawn()
-            .map_err(|e| format!("Failed to launch {bin}: {e}"))?;
+        let mut errors = Vec::new();
+        let mut tabs = Vec::new();
+        for (command, options) in commands {
+            if let Err(e) = options.check() {
+                errors.push(e);
+                continue;
+            }
+            let window = terminal.window(bin, command, options);
+            match terminal.build_remote_args(command, options) {
+                Some(args) if options.open_in == OpenIn::Tab => tabs.push((args, window)),
+                _ => errors.extend(spawn_window(bin, window).err()),
+            }
+        }
+        if !tabs.is_empty() {
+            std::thread::spawn(move || open_tabs(bin, tabs));
+        }
+
+        join_errors(errors)
+    }
+
+    /// Returns the process that opens the command in a new window, started
+    /// from `bin`.
+    fn window(self, bin: &str, command: &str, options: &LaunchOptions) -> Command {
+        let mut process = Command::new(bin);
+        process
+            .args(self.build_args(command, options))
+            .envs(&options.env);
+        if let Some(cwd) = &options.cwd {
+            // Also covers terminals that start the shell in their own directory
+            process.current_dir(cwd);
+        }
+        process
+    }
+
+    fn build_args(self, command: &str, options: &LaunchOptions) -> Vec<String> {
+        let mut args: Vec<String> = self.subcommand().iter().map(ToString::to_string).collect();
+        if options.open_in == OpenIn::Tab {
+            args.extend(self.tab_args().iter().map(ToString::to_string));
+        }
+
+        let cwd = match options.cwd.as_deref() {
+            Some(cwd) if !self.cwd_args().is_empty() => {
+                let path = cwd.to_string_lossy();
+                args.extend(self.cwd_args().iter().map(|a| a.replace("{}", &path)));
+                None
+            }
+            cwd => cwd,
+        };
+
+        let script = script(command, cwd, options);
+        let shell = ["sh", "-c", &script];
+        args.extend(self.exec_args().iter().map(ToString::to_string));
+        if self.joins_command() {
+            args.push(shell_words::join(shell));
+        } else {
+            args.extend(shell.map(String::from));
+        }
+        args
+    }
+
+    /// Returns the arguments that open a tab through the terminal's remote
+    /// control, or `None` if it has none.
+    fn build_remote_args(self, command: &str, options: &LaunchOptions) -> Option<Vec<String>> {
+        let (remote, exec) = self.remote_tab_args()?;
+        let mut args: Vec<String> = remote.iter().map(ToString::to_string).collect();
+
+        if let Some(cwd) = &options.cwd {
+            args.extend(["--cwd".to_string(), cwd.to_string_lossy().into_owned()]);
+        }
+
+        // The environment has to be set inside the tab
+        let script = format!(
+            "{}{}",
+            exports(&options.env),
+            script(command, None, options)
+        );
+        args.extend(exec.iter().map(ToString::to_string));
+        args.extend(["sh".to_string(), "-c".to_string(), script]);
+        Some(args)
+    }
+}
+
+/// Opens tabs through the remote control of `bin`, each given as its
+/// arguments along with the window to open instead. The remote control is
+/// no longer asked once it fails, e.g. because no instance is running.
+fn open_tabs(bin: &str, tabs: Vec<(Vec<String>, Command)>) {
+    let mut remote_works = true;
+    for (args, window) in tabs {
+        if remote_works {
+            let mut remote = Command::new(bin);
+            remote.args(args);
+            remote_works = succeeds_within(&mut remote, REMOTE_TIMEOUT);
+            if remote_works {
+                continue;
+            }
+        }
+        if let Err(e) = spawn_window(bin, window) {
+            eprintln!("Error: {e}");
+        }
+    }
+}
+
+/// Starts a terminal window.
+fn spawn_window(bin: &str, mut window: Command) -> Result<(), String> {
+    window
+        .spawn()
+        .map(drop)
+        .map_err(|e| format!("Failed to launch {bin}: {e}"))
+}
+
+/// Runs `command` without input or output, returning `true` if it succeeds
+/// before `timeout`. A command that takes longer is killed.
+fn succeeds_within(command: &mut Command, timeout: Duration) -> bool {
+    let Ok(mut child) = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
 
-        Ok(())
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if started.elapsed() < timeout => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
     }
 }
 
@@ -138,3 +355,207 @@ impl From<&str> for Terminal {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::path::PathBuf;
+
+    fn in_dir(dir: &str) -> LaunchOptions {
+        LaunchOptions {
+            cwd: Some(PathBuf::from(dir)),
+            ..LaunchOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_args_without_options() {
+        assert_eq!(
+            Terminal::Kitty.build_args("htop", &LaunchOptions::default()),
+            vec!["sh", "-c", "eval htop\nexec \"${SHELL:-sh}\""]
+        );
+    }
+
+    #[test]
+    fn test_cwd_flag() {
+        assert_eq!(
+            Terminal::Gnome.build_args("make", &in_dir("/src/infra")),
+            vec![
+                "--working-directory=/src/infra",
+                "--",
+                "sh",
+                "-c",
+                "eval make\nexec \"${SHELL:-sh}\""
+            ]
+        );
+        assert_eq!(
+            Terminal::Konsole.build_args("make", &in_dir("/src/infra"))[..2],
+            ["--workdir", "/src/infra"]
+        );
+    }
+
+    #[test]
+    fn test_cwd_flag_after_subcommand() {
+        assert_eq!(
+            Terminal::Wezterm.build_args("make", &in_dir("/src/infra"))[..3],
+            ["start", "--cwd", "/src/infra"]
+        );
+    }
+
+    #[test]
+    fn test_cwd_prefix_without_flag() {
+        assert_eq!(
+            Terminal::Xterm.build_args("make", &in_dir("/src/my infra")),
+            vec![
+                "-e",
+                "sh",
+                "-c",
+                "cd '/src/my infra' && eval make\nexec \"${SHELL:-sh}\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_joined_command_is_quoted() {
+        let args = Terminal::Tilix.build_args("echo $HOME", &LaunchOptions::default());
+        assert_eq!(args.len(), 2);
+        assert_eq!(
+            shell_words::split(&args[1]).unwrap(),
+            vec!["sh", "-c", "eval 'echo $HOME'\nexec \"${SHELL:-sh}\""]
+        );
+    }
+
+    /// Recovers the `sh -c` arguments from a terminal's arguments.
+    fn shell_argv(terminal: Terminal, args: &[String]) -> Vec<String> {
+        let rest = &args[terminal.subcommand().len()..];
+        let rest = &rest[terminal.exec_args().len()..];
+        if terminal.joins_command() {
+            shell_words::split(&rest[0]).unwrap()
+        } else {
+            rest.to_vec()
+        }
+    }
+
+    #[test]
+    fn test_awkward_commands_run_unchanged() {
+        for &terminal in Terminal::all() {
+            for command in testing::AWKWARD {
+                let args = terminal.build_args(command, &LaunchOptions::default());
+                assert_eq!(
+                    testing::run(&shell_argv(terminal, &args)),
+                    testing::expected(command),
+                    "{terminal:?}: {command}"
+                );
+            }
+        }
+    }
+
+    fn in_tab() -> LaunchOptions {
+        LaunchOptions {
+            cwd: Some(PathBuf::from("/src/infra")),
+            env: [("AWS_PROFILE".to_string(), "prod".to_string())].into(),
+            open_in: OpenIn::Tab,
+            ..LaunchOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_tab_flag() {
+        assert_eq!(
+            Terminal::Gnome.build_args("make", &in_tab())[..2],
+            ["--tab", "--working-directory=/src/infra"]
+        );
+        assert_eq!(
+            Terminal::Gnome.build_args("make", &LaunchOptions::default())[0],
+            "--"
+        );
+    }
+
+    #[test]
+    fn test_remote_tab() {
+        let script = "export AWS_PROFILE=prod\neval make\nexec \"${SHELL:-sh}\"";
+        assert_eq!(
+            Terminal::Kitty.build_remote_args("make", &in_tab()),
+            Some(
+                [
+                    "@",
+                    "launch",
+                    "--type=tab",
+                    "--cwd",
+                    "/src/infra",
+                    "sh",
+                    "-c",
+                    script
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+        assert_eq!(
+            Terminal::Wezterm.build_remote_args("make", &in_tab()),
+            Some(
+                [
+                    "cli",
+                    "--no-auto-start",
+                    "spawn",
+                    "--cwd",
+                    "/src/infra",
+                    "--",
+                    "sh",
+                    "-c",
+                    script
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+        assert_eq!(Terminal::Xterm.build_remote_args("make", &in_tab()), None);
+    }
+
+    #[test]
+    fn test_remote_command_times_out() {
+        assert!(succeeds_within(&mut Command::new("true"), REMOTE_TIMEOUT));
+        assert!(!succeeds_within(&mut Command::new("false"), REMOTE_TIMEOUT));
+
+        let started = Instant::now();
+        let mut hangs = Command::new("sleep");
+        hangs.arg("10");
+        assert!(!succeeds_within(&mut hangs, Duration::from_millis(100)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_remote_asked_once_after_failing() {
+        let dir = std::env::temp_dir().join(format!("xshuttle-tabs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let asked = dir.join("asked");
+        let opened = dir.join("opened");
+
+        let tab = || {
+            let args = ["-c", "echo >> \"$0\"; exit 1", asked.to_str().unwrap()];
+            let mut window = Command::new("sh");
+            window.args(["-c", "echo >> \"$0\"", opened.to_str().unwrap()]);
+            (args.map(String::from).to_vec(), window)
+        };
+        open_tabs("sh", vec![tab(), tab(), tab()]);
+
+        // Windows are spawned without waiting for them
+        let started = Instant::now();
+        let lines = |path: &PathBuf| std::fs::read_to_string(path).map_or(0, |s| s.lines().count());
+        while lines(&opened) < 3 && started.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(lines(&asked), 1);
+        assert_eq!(lines(&opened), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_cwd_rejected() {
+        let err = Terminal::Xterm
+            .launch("true", &in_dir("/nonexistent/xshuttle"))
+            .unwrap_err();
+        assert!(err.contains("/nonexistent/xshuttle"), "{err}");
+    }
+}
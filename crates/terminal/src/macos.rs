@@ -1,7 +1,10 @@
-use std::fs::{self, File};
+use crate::{LaunchOptions, exports, join_errors, script};
+use std::fs::OpenOptions;
 use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Terminal {
@@ -35,7 +38,7 @@ impl Terminal {
         }
     }
 
-    fn is_available(self) -> bool {
+    pub(crate) fn is_available(self) -> bool {
         self.app().is_some_and(is_app_installed)
     }
 
@@ -50,6 +53,20 @@ impl Terminal {
         ]
     }
 
+    /// Returns the names accepted in the `terminal` setting.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        [
+            "iterm",
+            "terminal",
+            "terminal.app",
+            "warp",
+            "kitty",
+            "ghostty",
+            "wezterm",
+        ]
+        .into_iter()
+    }
+
     fn detect(self) -> Option<Terminal> {
         // Prefer requested terminal if available
         if self != Self::Default && self.is_available() {
@@ -62,20 +79,27 @@ impl Terminal {
 
     /// Launches the given command in a terminal.
     ///
+    /// The command runs from a generated script, which changes into the
+    /// working directory and exports the environment variables in `options`
+    /// first. The script quotes the command, so it runs as written.
+    ///
     /// # Errors
     ///
-    /// Returns an error if no terminal is found, or if the terminal fails to launch.
+    /// Returns an error if no terminal is found, if the working directory
+    /// doesn't exist, or if the terminal fails to launch.
     ///
     /// # Panics
     ///
     /// Panics if `detect()` returns a terminal without an app name (should never happen).
-    pub fn launch(self, command: &str) -> Result<(), String> {
+    pub fn launch(self, command: &str, options: &LaunchOptions) -> Result<(), String> {
+        options.check()?;
         let terminal = self
             .detect()
             .ok_or("No terminal found. Install Terminal.app, iTerm, or Warp.")?;
 
         let app = terminal.app().expect("detect() never returns Default");
-        let script_path = create_script(command)?;
+        let script_path = create_script(&script_file(command, options))?;
+        let script_path = script_path.to_string_lossy();
 
         match terminal.custom_launch() {
             Some(args) => {
@@ -97,6 +121,20 @@ impl Terminal {
 
         Ok(())
     }
+
+    /// Launches several commands, each as [`Terminal::launch()`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming each command that fails to launch, after the
+    /// others have been launched.
+    pub(crate) fn launch_each(self, commands: &[(String, LaunchOptions)]) -> Result<(), String> {
+        join_errors(
+            commands
+                .iter()
+                .filter_map(|(command, options)| self.launch(command, options).err()),
+        )
+    }
 }
 
 impl From<&str> for Terminal {
@@ -124,18 +162,66 @@ fn is_app_installed(app: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn create_script(command: &str) -> Result<String, String> {
-    let script_path = "/tmp/xshuttle-run.sh";
-    let script_content = format!("#!/bin/zsh -il\n{command}\nexec $SHELL");
-
-    let mut file =
-        File::create(script_path).map_err(|e| format!("Failed to create script: {e}"))?;
+/// Returns a script that runs `command` with the options applied.
+///
+/// The script deletes itself first, since the terminal runs it after
+/// [`Terminal::launch()`] has returned.
+fn script_file(command: &str, options: &LaunchOptions) -> String {
+    format!(
+        "#!/bin/sh\nrm -f -- \"$0\"\n{}{}",
+        exports(&options.env),
+        script(command, options.cwd.as_deref(), options)
+    )
+}
 
-    file.write_all(script_content.as_bytes())
+/// Writes `content` to a new executable script and returns its path.
+fn create_script(content: &str) -> Result<PathBuf, String> {
+    // A file per launch, so quick successive launches don't overwrite a
+    // script before its terminal has read it
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let script_path = std::env::temp_dir().join(format!(
+        "xshuttle-run-{}-{}.sh",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o700)
+        .open(&script_path)
+        .map_err(|e| format!("Failed to create script: {e}"))?;
+
+    file.write_all(content.as_bytes())
         .map_err(|e| format!("Failed to write script: {e}"))?;
 
-    fs::set_permissions(script_path, fs::Permissions::from_mode(0o755))
-        .map_err(|e| format!("Failed to set permissions: {e}"))?;
+    Ok(script_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_awkward_commands_run_unchanged() {
+        for command in testing::AWKWARD {
+            let path = create_script(&script_file(command, &LaunchOptions::default())).unwrap();
+            let argv = ["sh".to_string(), path.to_string_lossy().into_owned()];
+            assert_eq!(testing::run(&argv), testing::expected(command), "{command}");
+            assert!(!path.exists(), "script should delete itself");
+        }
+    }
 
-    Ok(script_path.to_string())
+    #[test]
+    fn test_env_is_quoted() {
+        let options = LaunchOptions {
+            env: BTreeMap::from([("GREETING".to_string(), "it's $HOME".to_string())]),
+            ..LaunchOptions::default()
+        };
+        let path = create_script(&script_file("echo \"$GREETING\"", &options)).unwrap();
+        let argv = ["sh".to_string(), path.to_string_lossy().into_owned()];
+        assert_eq!(testing::run(&argv), "it's $HOME\n");
+    }
 }
@@ -0,0 +1,386 @@
+use crate::{KeepOpen, LaunchOptions, Launcher, exports, script};
+use std::ffi::OsStr;
+use std::process::{Command, Stdio};
+
+/// Session commands open in unless the config names another.
+pub const DEFAULT_SESSION: &str = "xshuttle";
+
+/// A tmux session that commands open in as new windows.
+///
+/// The session is created if it doesn't exist yet. If no client is attached
+/// to it, a terminal is opened that attaches to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tmux {
+    /// Name of the session.
+    pub session: String,
+    /// Socket name of the tmux server, as with `tmux -L`. `None` for the
+    /// default server.
+    pub socket: Option<String>,
+    /// Terminal to attach to the session in.
+    pub attach: Box<Launcher>,
+}
+
+impl Tmux {
+    pub(crate) fn is_available() -> bool {
+        which::which("tmux").is_ok()
+    }
+
+    /// Opens a window running the given command in the session, and a
+    /// terminal attached to the session if there's no client yet.
+    ///
+    /// The working directory in `options` is passed to tmux. Environment
+    /// variables are exported in the window, since tmux starts it from the
+    /// server's environment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the working directory doesn't exist, if tmux
+    /// fails to open the window, or if the terminal fails to launch.
+    pub fn launch(&self, command: &str, options: &LaunchOptions) -> Result<(), String> {
+        options.check()?;
+        self.open_window(command, options)?;
+        self.attach(options)
+    }
+
+    /// Opens one window named `title` with a pane for each command, tiled,
+    /// and a terminal attached to the session if there's no client yet.
+    ///
+    /// With `synchronize`, keys typed in one pane go to all of them, until
+    /// toggled off with tmux's `synchronize-panes` window option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a working directory doesn't exist, if tmux fails
+    /// to open the window or a pane, or if the terminal fails to launch.
+    pub fn launch_tiled(
+        &self,
+        title: &str,
+        commands: &[(String, LaunchOptions)],
+        synchronize: bool,
+    ) -> Result<(), String> {
+        let Some((_, options)) = commands.first() else {
+            return Ok(());
+        };
+        for (_, options) in commands {
+            options.check()?;
+        }
+        self.open_tiled(title, commands, synchronize)?;
+        self.attach(options)
+    }
+
+    /// Opens a terminal attached to the session, unless a client already is.
+    fn attach(&self, options: &LaunchOptions) -> Result<(), String> {
+        if self.is_attached() {
+            return Ok(());
+        }
+        let attach = shell_words::join(["tmux"].into_iter().chain(self.server_args()).chain([
+            "attach-session",
+            "-t",
+            &self.target(),
+        ]));
+        let options = LaunchOptions {
+            title: options.title.clone(),
+            keep_open: KeepOpen::Close,
+            shell: options.shell.clone(),
+            ..LaunchOptions::default()
+        };
+        self.attach.launch(&attach, &options)
+    }
+
+    /// Exact-match target for the session, so `main` doesn't pick `main2`.
+    fn target(&self) -> String {
+        format!("={}", self.session)
+    }
+
+    fn server_args(&self) -> Vec<&str> {
+        match &self.socket {
+            Some(socket) => vec!["-L", socket],
+            None => Vec::new(),
+        }
+    }
+
+    fn tmux(&self) -> Command {
+        let mut tmux = Command::new("tmux");
+        tmux.args(self.server_args())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        tmux
+    }
+
+    fn has_session(&self) -> bool {
+        self.tmux()
+            .args(["has-session", "-t", &self.target()])
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    fn is_attached(&self) -> bool {
+        self.tmux()
+            .args(["list-clients", "-t", &self.target()])
+            .stdout(Stdio::piped())
+            .output()
+            .is_ok_and(|output| !output.stdout.is_empty())
+    }
+
+    /// Runs a tmux command, returning what it printed.
+    fn run<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<String, String> {
+        let output = self
+            .tmux()
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| format!("Failed to launch tmux: {e}"))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(format!(
+                "tmux failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
+    /// Opens a window running the given command, returning its ID.
+    fn open_window(&self, command: &str, options: &LaunchOptions) -> Result<String, String> {
+        self.run(&self.build_args(command, options, self.has_session()))
+    }
+
+    fn open_tiled(
+        &self,
+        title: &str,
+        commands: &[(String, LaunchOptions)],
+        synchronize: bool,
+    ) -> Result<(), String> {
+        let Some(((first, options), rest)) = commands.split_first() else {
+            return Ok(());
+        };
+        let options = LaunchOptions {
+            title: Some(title.to_string()),
+            ..options.clone()
+        };
+        let window = self.open_window(first, &options)?;
+
+        for (command, options) in rest {
+            let mut args = vec!["split-window".to_string(), "-t".to_string(), window.clone()];
+            args.extend(pane_args(command, options));
+            self.run(&args)?;
+            // Retiling after each split leaves room for the next one
+            self.run(&["select-layout", "-t", &window, "tiled"])?;
+        }
+
+        if synchronize {
+            self.run(&[
+                "set-window-option",
+                "-t",
+                &window,
+                "synchronize-panes",
+                "on",
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Returns the arguments that open a window in the session, or create
+    /// the session with it if `exists` is `false`. tmux prints the window's
+    /// ID.
+    fn build_args(&self, command: &str, options: &LaunchOptions, exists: bool) -> Vec<String> {
+        let mut args: Vec<String> = if exists {
+            vec![
+                "new-window".into(),
+                "-t".into(),
+                format!("{}:", self.target()),
+            ]
+        } else {
+            vec![
+                "new-session".into(),
+                "-d".into(),
+                "-s".into(),
+                self.session.clone(),
+            ]
+        };
+        args.extend(["-P".to_string(), "-F".to_string(), WINDOW_ID.to_string()]);
+
+        if let Some(title) = &options.title {
+            args.extend(["-n".to_string(), title.clone()]);
+        }
+        args.extend(pane_args(command, options));
+        args
+    }
+}
+
+/// Format that makes tmux print the ID of a window it opened.
+const WINDOW_ID: &str = "#{window_id}";
+
+/// Returns the arguments that start a pane in the working directory from
+/// `options`, running the given command.
+fn pane_args(command: &str, options: &LaunchOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(cwd) = &options.cwd {
+        args.extend(["-c".to_string(), cwd.to_string_lossy().into_owned()]);
+    }
+
+    let script = format!(
+        "{}{}",
+        exports(&options.env),
+        script(command, None, options)
+    );
+    args.extend(["sh".to_string(), "-c".to_string(), script]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Terminal;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    fn tmux(socket: Option<&str>) -> Tmux {
+        Tmux {
+            session: "main".to_string(),
+            socket: socket.map(String::from),
+            attach: Box::new(Launcher::BuiltIn(Terminal::Default)),
+        }
+    }
+
+    #[test]
+    fn test_new_session_args() {
+        let options = LaunchOptions {
+            cwd: Some(PathBuf::from("/src/infra")),
+            title: Some("Deploy".to_string()),
+            keep_open: KeepOpen::Close,
+            ..LaunchOptions::default()
+        };
+        assert_eq!(
+            tmux(None).build_args("make deploy", &options, false),
+            vec![
+                "new-session",
+                "-d",
+                "-s",
+                "main",
+                "-P",
+                "-F",
+                "#{window_id}",
+                "-n",
+                "Deploy",
+                "-c",
+                "/src/infra",
+                "sh",
+                "-c",
+                "eval 'make deploy'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_window_args() {
+        let options = LaunchOptions {
+            env: [("AWS_PROFILE".to_string(), "prod".to_string())].into(),
+            keep_open: KeepOpen::Close,
+            ..LaunchOptions::default()
+        };
+        assert_eq!(
+            tmux(None).build_args("htop", &options, true),
+            vec![
+                "new-window",
+                "-t",
+                "=main:",
+                "-P",
+                "-F",
+                "#{window_id}",
+                "sh",
+                "-c",
+                "export AWS_PROFILE=prod\neval htop"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_opens_windows_in_local_server() {
+        if !Tmux::is_available() {
+            return;
+        }
+        let socket = format!("xshuttle-test-{}", std::process::id());
+        let tmux = tmux(Some(&socket));
+        let dir = std::env::temp_dir().join(&socket);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = LaunchOptions {
+            cwd: Some(dir.clone()),
+            env: [("GREETING".to_string(), "it's $HOME".to_string())].into(),
+            title: Some("First".to_string()),
+            ..LaunchOptions::default()
+        };
+        tmux.open_window("echo \"$GREETING\" > first", &options)
+            .unwrap();
+        assert!(tmux.has_session());
+        assert!(!tmux.is_attached());
+
+        let options = LaunchOptions {
+            title: Some("Second".to_string()),
+            ..options
+        };
+        tmux.open_window("pwd > second", &options).unwrap();
+
+        let windows = tmux.run(&["list-windows", "-t", "=main", "-F", "#{window_name}"]);
+        let read = |name: &str| {
+            let started = Instant::now();
+            loop {
+                match std::fs::read_to_string(dir.join(name)) {
+                    Ok(content) if !content.is_empty() => return content,
+                    _ if started.elapsed() > Duration::from_secs(5) => return String::new(),
+                    _ => std::thread::sleep(Duration::from_millis(20)),
+                }
+            }
+        };
+        let first = read("first");
+        let second = read("second");
+        let dir_path = dir.canonicalize().unwrap();
+
+        let _ = tmux.tmux().arg("kill-server").status();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(windows.unwrap(), "First\nSecond");
+        assert_eq!(first, "it's $HOME\n");
+        assert_eq!(second.trim(), dir_path.to_string_lossy());
+    }
+
+    #[test]
+    fn test_opens_tiled_panes_in_local_server() {
+        if !Tmux::is_available() {
+            return;
+        }
+        let socket = format!("xshuttle-test-tiled-{}", std::process::id());
+        let tmux = tmux(Some(&socket));
+
+        let commands: Vec<_> = ["web-1", "web-2", "web-3"]
+            .into_iter()
+            .map(|host| {
+                let options = LaunchOptions {
+                    title: Some(host.to_string()),
+                    ..LaunchOptions::default()
+                };
+                (format!("echo {host}"), options)
+            })
+            .collect();
+        tmux.open_tiled("Web", &commands, true).unwrap();
+
+        let windows = tmux.run(&["list-windows", "-t", "=main", "-F", "#{window_name}"]);
+        let panes = tmux.run(&["list-panes", "-t", "=main:Web"]);
+        let synchronized = tmux.run(&[
+            "show-window-options",
+            "-v",
+            "-t",
+            "=main:Web",
+            "synchronize-panes",
+        ]);
+        let _ = tmux.tmux().arg("kill-server").status();
+
+        assert_eq!(windows.unwrap(), "Web");
+        assert_eq!(panes.unwrap().lines().count(), 3);
+        assert_eq!(synchronized.unwrap(), "on");
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt;
 
 use image::load_from_memory;
-use settings::{Action, Node, Nodes, Settings};
+use settings::{Action, Diagnostic, Node, Nodes, Settings, Severity};
 use tray_icon::menu::{MenuItem, PredefinedMenuItem, Submenu};
 use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
 
@@ -12,9 +12,104 @@ pub const MENU_ID_RELOAD: &str = "reload";
 pub const MENU_ID_QUIT: &str = "quit";
 pub const MENU_ID_ACTION_PREFIX: &str = "action_";
 pub const MENU_ID_HOST_PREFIX: &str = "host_";
+/// Prefix of "Open all" items, followed by the group's path as
+/// dot-separated indexes, e.g. `group_1.0`.
+pub const MENU_ID_GROUP_PREFIX: &str = "group_";
+/// Prefix of "Stop" items for running jobs, followed by the job's ID.
+pub const MENU_ID_STOP_PREFIX: &str = "stop_";
+/// Prefix of "Show output" items for running jobs, followed by the job's ID.
+pub const MENU_ID_OUTPUT_PREFIX: &str = "output_";
+pub const MENU_ID_OPEN_ERROR: &str = "open_error";
+pub const MENU_ID_COPY_ERROR: &str = "copy_error";
+
+/// Maximum number of config problems listed in the menu.
+const MAX_DIAGNOSTICS: usize = 5;
+/// Maximum length of a status line in the menu, in characters.
+const MAX_LABEL_LEN: usize = 80;
 
 const ICON_BYTES: &[u8] = include_bytes!("../../../assets/icon.png");
 
+/// Tooltip of the tray icon while nothing is running.
+const TOOLTIP: &str = "xshuttle";
+
+/// Problems to show at the top of the menu.
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    /// Problems from the most recent attempt to load settings.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The most recent failed launch, until the next successful one.
+    pub launch_failure: Option<LaunchFailure>,
+}
+
+impl Status {
+    /// Returns `true` if there is nothing to show.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty() && self.launch_failure.is_none()
+    }
+
+    /// Returns the diagnostic "Open config at error" should jump to.
+    ///
+    /// Errors take precedence over warnings; diagnostics without a file are
+    /// skipped.
+    pub fn first_located(&self) -> Option<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.file.is_some())
+            .max_by_key(|d| d.severity)
+    }
+
+    /// Returns the full text of every problem, one per line.
+    pub fn report(&self) -> String {
+        self.diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .chain(self.launch_failure.iter().map(ToString::to_string))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A command that could not be launched.
+#[derive(Debug, Clone)]
+pub struct LaunchFailure {
+    /// Name of the action or host that was launched.
+    pub name: String,
+    /// Why launching failed.
+    pub message: String,
+}
+
+impl fmt::Display for LaunchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to launch '{}': {}", self.name, self.message)
+    }
+}
+
+/// A background action to list in the "Running" submenu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningJob {
+    /// ID the job's menu items refer to it by.
+    pub id: u32,
+    /// Name of the action.
+    pub name: String,
+    /// Whole minutes the job has been running, so the menu only needs
+    /// rebuilding once a minute.
+    pub minutes: u64,
+    /// Whether the job has been asked to stop.
+    pub stopping: bool,
+}
+
+impl RunningJob {
+    fn label(&self) -> String {
+        let elapsed = match self.minutes {
+            0 => "<1m".to_string(),
+            minutes @ 1..60 => format!("{minutes}m"),
+            minutes => format!("{}h {}m", minutes / 60, minutes % 60),
+        };
+        let stopping = if self.stopping { ", stopping" } else { "" };
+        format!("{} ({elapsed}{stopping})", self.name)
+    }
+}
+
 pub struct Tray {
     icon: Option<TrayIcon>,
 }
@@ -48,7 +143,7 @@ impl Tray {
         self.icon = Some(
             TrayIconBuilder::new()
                 .with_menu(Box::new(menu))
-                .with_tooltip("xshuttle")
+                .with_tooltip(TOOLTIP)
                 .with_icon(load_icon())
                 .build()
                 .expect("Failed to create tray icon"),
@@ -61,6 +156,20 @@ impl Tray {
         }
     }
 
+    /// Shows how many jobs are running in the tooltip.
+    pub fn set_running(&self, count: usize) {
+        let tooltip = match count {
+            0 => TOOLTIP.to_string(),
+            1 => format!("{TOOLTIP} – 1 job running"),
+            count => format!("{TOOLTIP} – {count} jobs running"),
+        };
+        if let Some(icon) = &self.icon
+            && let Err(e) = icon.set_tooltip(Some(tooltip))
+        {
+            eprintln!("Warning: Failed to set tooltip: {e}");
+        }
+    }
+
     pub fn destroy(&mut self) {
         self.icon.take();
     }
@@ -78,13 +187,18 @@ fn load_icon() -> Icon {
 ///
 /// Uses the indexed `Nodes<T>` containers for O(1) lookup.
 /// Menu item IDs are formatted as `node_{index}` for dynamic entries.
+/// Problems in `status` are listed first, as disabled items, followed by
+/// the `running` jobs.
 ///
 /// # Panics
 ///
 /// Panics if menu items cannot be appended to the menu.
-pub fn build_menu(settings: &Settings) -> Menu {
+pub fn build_menu(settings: &Settings, status: &Status, running: &[RunningJob]) -> Menu {
     let menu = Menu::new();
 
+    build_status(&menu, status);
+    build_running(&menu, running);
+
     // Build action entries (with submenus)
     build_action_nodes(&menu, settings.actions.nodes(), &settings.actions);
 
@@ -125,8 +239,110 @@ pub fn build_menu(settings: &Settings) -> Menu {
     menu
 }
 
+fn build_status(menu: &Menu, status: &Status) {
+    if status.is_empty() {
+        return;
+    }
+
+    for diagnostic in status.diagnostics.iter().take(MAX_DIAGNOSTICS) {
+        let label = match diagnostic.severity {
+            Severity::Error => format!("⚠ Config error: {}", describe(diagnostic)),
+            Severity::Warning => format!("⚠ Skipped: {}", describe(diagnostic)),
+        };
+        menu.append(&MenuItem::new(truncate(&label), false, None))
+            .expect("Failed to append menu item");
+    }
+    if status.diagnostics.len() > MAX_DIAGNOSTICS {
+        let label = format!("… and {} more", status.diagnostics.len() - MAX_DIAGNOSTICS);
+        menu.append(&MenuItem::new(label, false, None))
+            .expect("Failed to append menu item");
+    }
+
+    if let Some(failure) = &status.launch_failure {
+        let label = format!("⚠ {failure}");
+        menu.append(&MenuItem::new(truncate(&label), false, None))
+            .expect("Failed to append menu item");
+    }
+
+    if status.first_located().is_some() {
+        menu.append(&MenuItem::with_id(
+            MENU_ID_OPEN_ERROR,
+            "Open config at error",
+            true,
+            None,
+        ))
+        .expect("Failed to append menu item");
+    }
+    menu.append(&MenuItem::with_id(
+        MENU_ID_COPY_ERROR,
+        "Copy error",
+        true,
+        None,
+    ))
+    .expect("Failed to append menu item");
+
+    menu.append(&PredefinedMenuItem::separator()).unwrap();
+}
+
+fn build_running(menu: &Menu, running: &[RunningJob]) {
+    if running.is_empty() {
+        return;
+    }
+
+    let submenu = Submenu::new(format!("Running ({})", running.len()), true);
+    for job in running {
+        let item = Submenu::new(truncate(&job.label()), true);
+        item.append(&MenuItem::with_id(
+            format!("{MENU_ID_STOP_PREFIX}{}", job.id),
+            "Stop",
+            true,
+            None,
+        ))
+        .expect("Failed to append menu item");
+        item.append(&MenuItem::with_id(
+            format!("{MENU_ID_OUTPUT_PREFIX}{}", job.id),
+            "Show output",
+            true,
+            None,
+        ))
+        .expect("Failed to append menu item");
+        submenu.append(&item).expect("Failed to append submenu");
+    }
+    menu.append(&submenu).expect("Failed to append submenu");
+
+    menu.append(&PredefinedMenuItem::separator()).unwrap();
+}
+
+/// Describes a diagnostic briefly, naming only the file, not its directory.
+fn describe(diagnostic: &Diagnostic) -> String {
+    let file = diagnostic
+        .file
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy());
+
+    match (file, diagnostic.location) {
+        (Some(file), Some(location)) => {
+            format!("{file}:{}: {}", location.line, diagnostic.message)
+        }
+        (Some(file), None) => format!("{file}: {}", diagnostic.message),
+        (None, _) => diagnostic.message.clone(),
+    }
+}
+
+/// Shortens a label to fit in the menu, keeping it on one line.
+fn truncate(label: &str) -> String {
+    let line = label.lines().next().unwrap_or_default();
+    if line.chars().count() <= MAX_LABEL_LEN && line.len() == label.len() {
+        return line.to_string();
+    }
+    let mut short: String = line.chars().take(MAX_LABEL_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
 fn build_action_nodes(menu: &Menu, tree: &[Node<Action>], actions: &Nodes<Action>) {
-    for node in tree {
+    for (index, node) in tree.iter().enumerate() {
         match node {
             Node::Leaf { id, .. } => {
                 if let Some(action) = actions.get(*id) {
@@ -137,15 +353,36 @@ fn build_action_nodes(menu: &Menu, tree: &[Node<Action>], actions: &Nodes<Action
             }
             Node::Group { name, children } => {
                 let submenu = Submenu::new(name, true);
-                build_action_submenu(&submenu, children, actions);
+                let path = index.to_string();
+                build_open_all(&submenu, node, &path);
+                build_action_submenu(&submenu, children, actions, &path);
                 menu.append(&submenu).expect("Failed to append submenu");
             }
         }
     }
 }
 
-fn build_action_submenu(submenu: &Submenu, tree: &[Node<Action>], actions: &Nodes<Action>) {
-    for node in tree {
+/// Adds an "Open all" item for a group with more than one action in it.
+fn build_open_all(submenu: &Submenu, group: &Node<Action>, path: &str) {
+    if group.leaf_ids().len() < 2 {
+        return;
+    }
+    let menu_id = format!("{MENU_ID_GROUP_PREFIX}{path}");
+    submenu
+        .append(&MenuItem::with_id(menu_id, "Open all", true, None))
+        .expect("Failed to append menu item");
+    submenu
+        .append(&PredefinedMenuItem::separator())
+        .expect("Failed to append separator");
+}
+
+fn build_action_submenu(
+    submenu: &Submenu,
+    tree: &[Node<Action>],
+    actions: &Nodes<Action>,
+    path: &str,
+) {
+    for (index, node) in tree.iter().enumerate() {
         match node {
             Node::Leaf { id, .. } => {
                 if let Some(action) = actions.get(*id) {
@@ -158,7 +395,9 @@ fn build_action_submenu(submenu: &Submenu, tree: &[Node<Action>], actions: &Node
             }
             Node::Group { name, children } => {
                 let nested = Submenu::new(name, true);
-                build_action_submenu(&nested, children, actions);
+                let path = format!("{path}.{index}");
+                build_open_all(&nested, node, &path);
+                build_action_submenu(&nested, children, actions, &path);
                 submenu.append(&nested).expect("Failed to append submenu");
             }
         }
@@ -0,0 +1,45 @@
+//! Copying text to the system clipboard.
+
+/// Places `text` on the system clipboard.
+///
+/// On Linux this uses the GTK clipboard and must be called on the GTK
+/// thread.
+#[cfg(target_os = "linux")]
+#[expect(
+    clippy::unnecessary_wraps,
+    reason = "copying can fail on other platforms"
+)]
+pub fn copy(text: &str) -> Result<(), String> {
+    let clipboard = gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD);
+    clipboard.set_text(text);
+    // Hand the text to a clipboard manager, if any, so it outlives us
+    clipboard.store();
+    Ok(())
+}
+
+/// Places `text` on the system clipboard.
+#[cfg(target_os = "macos")]
+pub fn copy(text: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("pbcopy")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run pbcopy: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write to pbcopy: {e}"))?;
+    }
+    child
+        .wait()
+        .map_err(|e| format!("Failed to wait for pbcopy: {e}"))?;
+    Ok(())
+}
+
+/// Places `text` on the system clipboard.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn copy(_text: &str) -> Result<(), String> {
+    Err("Copying to the clipboard is not supported on this platform".to_string())
+}
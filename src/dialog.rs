@@ -0,0 +1,228 @@
+//! Native dialogs.
+
+/// Title of every dialog.
+const TITLE: &str = "xshuttle";
+/// Label of the button that goes ahead.
+const CONFIRM_BUTTON: &str = "Run";
+
+/// A value to ask for in [`prompt()`].
+pub struct Field<'a> {
+    /// What to ask for.
+    pub label: &'a str,
+    /// The options to pick from, or empty for free text.
+    pub choices: &'a [String],
+    /// The value to start with, if any.
+    pub value: Option<&'a str>,
+}
+
+/// Asks the user to confirm `message`, returning `true` if they did.
+///
+/// Cancel is the default, so pressing Enter on a stray click doesn't go
+/// ahead. On Linux this uses GTK and must be called on the GTK thread.
+#[cfg(target_os = "linux")]
+pub fn confirm(message: &str) -> bool {
+    use gtk::prelude::*;
+
+    let dialog = gtk::MessageDialog::new(
+        None::<&gtk::Window>,
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Warning,
+        gtk::ButtonsType::None,
+        message,
+    );
+    dialog.set_title(TITLE);
+    dialog.set_keep_above(true);
+    dialog.add_buttons(&[
+        ("Cancel", gtk::ResponseType::Cancel),
+        (CONFIRM_BUTTON, gtk::ResponseType::Accept),
+    ]);
+    dialog.set_default_response(gtk::ResponseType::Cancel);
+
+    let response = dialog.run();
+    dialog.close();
+    response == gtk::ResponseType::Accept
+}
+
+/// Asks the user to confirm `message`, returning `true` if they did.
+///
+/// Cancel is the default, so pressing Enter on a stray click doesn't go
+/// ahead.
+#[cfg(target_os = "macos")]
+pub fn confirm(message: &str) -> bool {
+    use std::process::Command;
+
+    let script = format!(
+        "display dialog {} with title {} buttons {{\"Cancel\", {}}} \
+         default button \"Cancel\" cancel button \"Cancel\" with icon caution",
+        applescript_string(message),
+        applescript_string(TITLE),
+        applescript_string(CONFIRM_BUTTON),
+    );
+    // Cancelling makes osascript fail
+    Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Asks the user for the values of `fields`, returning them in the same
+/// order, or `None` if they cancel.
+///
+/// All fields are asked for in one dialog, and Enter in a text field goes
+/// ahead. On Linux this uses GTK and must be called on the GTK thread.
+#[cfg(target_os = "linux")]
+pub fn prompt(title: &str, fields: &[Field]) -> Option<Vec<String>> {
+    use gtk::prelude::*;
+
+    enum Input {
+        Text(gtk::Entry),
+        Choice(gtk::ComboBoxText),
+    }
+
+    let dialog = gtk::Dialog::with_buttons(
+        Some(title),
+        None::<&gtk::Window>,
+        gtk::DialogFlags::MODAL,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            (CONFIRM_BUTTON, gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_keep_above(true);
+    dialog.set_default_response(gtk::ResponseType::Accept);
+
+    let grid = gtk::Grid::new();
+    grid.set_border_width(12);
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(12);
+
+    let mut inputs = Vec::new();
+    for (row, field) in (0..).zip(fields) {
+        let label = gtk::Label::new(Some(field.label));
+        label.set_xalign(0.0);
+        grid.attach(&label, 0, row, 1, 1);
+
+        let input = if field.choices.is_empty() {
+            let entry = gtk::Entry::new();
+            entry.set_text(field.value.unwrap_or_default());
+            entry.set_activates_default(true);
+            entry.set_hexpand(true);
+            grid.attach(&entry, 1, row, 1, 1);
+            Input::Text(entry)
+        } else {
+            let combo = gtk::ComboBoxText::new();
+            for choice in field.choices {
+                combo.append(Some(choice.as_str()), choice);
+            }
+            if !field
+                .value
+                .is_some_and(|value| combo.set_active_id(Some(value)))
+            {
+                combo.set_active(Some(0));
+            }
+            combo.set_hexpand(true);
+            grid.attach(&combo, 1, row, 1, 1);
+            Input::Choice(combo)
+        };
+        inputs.push(input);
+    }
+    dialog.content_area().add(&grid);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let values = (response == gtk::ResponseType::Accept).then(|| {
+        inputs
+            .iter()
+            .map(|input| match input {
+                Input::Text(entry) => entry.text().to_string(),
+                Input::Choice(combo) => combo
+                    .active_text()
+                    .map(|text| text.to_string())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    });
+    dialog.close();
+    values
+}
+
+/// Asks the user for the values of `fields`, returning them in the same
+/// order, or `None` if they cancel.
+///
+/// Each field is asked for in a dialog of its own.
+#[cfg(target_os = "macos")]
+pub fn prompt(title: &str, fields: &[Field]) -> Option<Vec<String>> {
+    fields
+        .iter()
+        .map(|field| {
+            let value = field
+                .value
+                .or_else(|| field.choices.first().map(String::as_str))
+                .unwrap_or_default();
+            let value = applescript_string(value);
+            let script = if field.choices.is_empty() {
+                format!(
+                    "text returned of (display dialog {} with title {} default answer {value} \
+                     buttons {{\"Cancel\", {}}} default button {} cancel button \"Cancel\")",
+                    applescript_string(field.label),
+                    applescript_string(title),
+                    applescript_string(CONFIRM_BUTTON),
+                    applescript_string(CONFIRM_BUTTON),
+                )
+            } else {
+                let choices: Vec<String> = field
+                    .choices
+                    .iter()
+                    .map(String::as_str)
+                    .map(applescript_string)
+                    .collect();
+                // Cancelling returns false rather than failing
+                format!(
+                    "set choice to choose from list {{{}}} with title {} with prompt {} \
+                     default items {{{value}}} OK button name {}\n\
+                     if choice is false then error number -128\n\
+                     item 1 of choice",
+                    choices.join(", "),
+                    applescript_string(title),
+                    applescript_string(field.label),
+                    applescript_string(CONFIRM_BUTTON),
+                )
+            };
+            osascript(&script)
+        })
+        .collect()
+}
+
+/// Runs an `AppleScript`, returning what it prints without the trailing
+/// newline, or `None` if it fails, as it does when the user cancels.
+#[cfg(target_os = "macos")]
+fn osascript(script: &str) -> Option<String> {
+    let output = std::process::Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    Some(text.strip_suffix('\n').unwrap_or(&text).to_string())
+}
+
+/// Quotes `text` as an `AppleScript` string literal.
+#[cfg(target_os = "macos")]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Asks the user to confirm `message`, returning `true` if they did.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn confirm(message: &str) -> bool {
+    eprintln!("Warning: Can't ask for confirmation on this platform: {message}");
+    false
+}
+
+/// Asks the user for the values of `fields`, returning them in the same
+/// order, or `None` if they cancel.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn prompt(title: &str, _fields: &[Field]) -> Option<Vec<String>> {
+    eprintln!("Warning: Can't ask for input on this platform: {title}");
+    None
+}
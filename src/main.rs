@@ -1,6 +1,9 @@
+mod clipboard;
+mod dialog;
 mod xshuttle;
 
 use clap::Parser;
+use std::path::PathBuf;
 use tray::MenuEvent;
 use winit::application::ApplicationHandler;
 use winit::event::StartCause;
@@ -9,9 +12,16 @@ use xshuttle::{Application, UserEvent};
 
 const VERSION: &str = concat!(env!("XSHUTTLE_VERSION"), " ", env!("XSHUTTLE_BUILD_HASH"));
 
+/// How often the event loop wakes up for periodic work such as config watching.
+const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
 #[derive(Parser)]
 #[command(name = "xshuttle", version = VERSION)]
-struct Arguments {}
+struct Arguments {
+    /// Path to the config file, overriding the default lookup
+    #[arg(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
 
 impl ApplicationHandler<UserEvent> for Application {
     fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
@@ -26,6 +36,20 @@ impl ApplicationHandler<UserEvent> for Application {
 
     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
 
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // On Linux, the GTK thread drives the application
+        #[cfg(target_os = "linux")]
+        let _ = event_loop;
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.tick();
+            event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(
+                std::time::Instant::now() + TICK_INTERVAL,
+            ));
+        }
+    }
+
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
         let UserEvent::MenuEvent(event) = event;
 
@@ -61,17 +85,22 @@ fn wake_macos_run_loop() {
 }
 
 #[cfg(target_os = "linux")]
-fn run_gtk_thread(quit_proxy: winit::event_loop::EventLoopProxy<UserEvent>) {
+fn run_gtk_thread(
+    quit_proxy: winit::event_loop::EventLoopProxy<UserEvent>,
+    config_path: Option<PathBuf>,
+) {
     use tray::MenuId;
 
     std::thread::spawn(move || {
         gtk::init().unwrap();
 
-        let mut app = Application::default();
+        let mut app = Application::new(config_path);
         app.init();
 
         let receiver = MenuEvent::receiver();
-        gtk::glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+        gtk::glib::timeout_add_local(TICK_INTERVAL, move || {
+            app.tick();
+
             while let Ok(event) = receiver.try_recv() {
                 if app.handle_menu_event(&event) {
                     let quit_event = MenuEvent {
@@ -90,18 +119,24 @@ fn run_gtk_thread(quit_proxy: winit::event_loop::EventLoopProxy<UserEvent>) {
 }
 
 fn main() {
-    Arguments::parse();
+    let args = Arguments::parse();
 
     let event_loop = EventLoop::<UserEvent>::with_user_event().build().unwrap();
     let proxy = event_loop.create_proxy();
 
     #[cfg(target_os = "linux")]
-    run_gtk_thread(proxy);
+    run_gtk_thread(proxy, args.config);
 
     #[cfg(not(target_os = "linux"))]
     MenuEvent::set_event_handler(Some(move |event| {
         let _ = proxy.send_event(UserEvent::MenuEvent(event));
     }));
 
-    event_loop.run_app(&mut Application::default()).unwrap();
+    // On Linux, the application lives on the GTK thread
+    #[cfg(target_os = "linux")]
+    let mut app = Application::default();
+    #[cfg(not(target_os = "linux"))]
+    let mut app = Application::new(args.config);
+
+    event_loop.run_app(&mut app).unwrap();
 }
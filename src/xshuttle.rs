@@ -1,8 +1,22 @@
-use settings::{NodeId, Settings};
-use terminal::Terminal;
+use crate::{clipboard, dialog};
+use notification::{DesktopNotifier, Notification, Notifier};
+use settings::{
+    Action, Diagnostic, Location, Mode, Node, NodeId, Settings, SettingsError, Severity, Template,
+    Watcher, did_you_mean,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use terminal::{
+    Custom, DEFAULT_SESSION, Job, KeepOpen, LaunchOptions, Launcher, OpenIn, Shell, Terminals,
+};
 use tray::{
-    MENU_ID_ACTION_PREFIX, MENU_ID_CONFIGURE, MENU_ID_HOST_PREFIX, MENU_ID_QUIT, MENU_ID_RELOAD,
-    Menu, MenuEvent, Tray, build_menu,
+    LaunchFailure, MENU_ID_ACTION_PREFIX, MENU_ID_CONFIGURE, MENU_ID_COPY_ERROR,
+    MENU_ID_GROUP_PREFIX, MENU_ID_HOST_PREFIX, MENU_ID_OPEN_ERROR, MENU_ID_OUTPUT_PREFIX,
+    MENU_ID_QUIT, MENU_ID_RELOAD, MENU_ID_STOP_PREFIX, Menu, MenuEvent, RunningJob, Status, Tray,
+    build_menu,
 };
 
 #[derive(Debug)]
@@ -10,37 +24,164 @@ pub enum UserEvent {
     MenuEvent(MenuEvent),
 }
 
-#[derive(Default)]
+/// A command to launch in a terminal.
+struct Launch {
+    /// Name of the menu item, for error reporting.
+    name: String,
+    command: String,
+    /// Launch options, or why they couldn't be determined.
+    options: Result<LaunchOptions, String>,
+    /// Terminals to try instead of the global setting, if any.
+    terminal: Vec<String>,
+    /// Whether to run the command in the background instead of a terminal.
+    background: bool,
+    /// Question to confirm before launching, if any.
+    confirm: Option<String>,
+    /// Values to ask for and fill into `command` before launching, if any.
+    template: Option<Template>,
+}
+
+impl Launch {
+    /// Returns `true` unless the launch needs confirming and the user
+    /// declines.
+    fn is_confirmed(&self) -> bool {
+        self.confirm.as_deref().is_none_or(dialog::confirm)
+    }
+}
+
+/// A background action that is still running.
+#[derive(Debug)]
+struct Running {
+    /// Name of the action.
+    name: String,
+    job: Job,
+}
+
+/// Maximum number of config problems listed in a notification.
+const MAX_NOTIFIED_PROBLEMS: usize = 3;
+/// How long a stopped job gets to exit before it's killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a killed job's processes get to go away before it's given up on.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often a stopped job's process group is checked for processes left.
+const GROUP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Number of log files kept for each background action.
+const KEPT_LOGS: usize = 10;
+
 pub struct Application {
+    config_path: Option<PathBuf>,
     settings: Option<Settings>,
+    status: Status,
     tray: Tray,
+    watcher: Watcher,
+    notifier: Box<dyn Notifier>,
+    /// Background actions that haven't finished yet.
+    jobs: Vec<Running>,
+    /// The jobs as last listed in the menu.
+    shown_jobs: Vec<RunningJob>,
+    /// The values last filled into each action's placeholders, by action
+    /// name and placeholder label.
+    last_values: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for Application {
+    fn default() -> Self {
+        Self {
+            config_path: None,
+            settings: None,
+            status: Status::default(),
+            tray: Tray::default(),
+            watcher: Watcher::default(),
+            notifier: Box::new(DesktopNotifier::new()),
+            jobs: Vec::new(),
+            shown_jobs: Vec::new(),
+            last_values: HashMap::new(),
+        }
+    }
 }
 
 impl Application {
+    /// Creates an application using the given config file, or the default
+    /// config path if `None`.
+    pub fn new(config_path: Option<PathBuf>) -> Self {
+        Self {
+            config_path: config_path.or_else(Settings::config_path),
+            ..Self::default()
+        }
+    }
+
     pub fn init(&mut self) {
-        if let Err(e) = Settings::ensure_config_exists() {
-            eprintln!("Warning: Could not ensure config exists: {e}");
+        if let Some(path) = &self.config_path {
+            if let Err(e) = Settings::ensure_config_exists(path) {
+                eprintln!("Warning: Could not ensure config exists: {e}");
+            }
+            self.watcher = Watcher::new(Settings::watch_paths(path));
         }
 
         let menu = self.build();
         self.tray.init(menu);
+
+        if !self.status.diagnostics.is_empty() {
+            self.notify(&Notification::error(
+                "Config has problems",
+                summarize(&self.status.diagnostics),
+            ));
+        }
+    }
+
+    /// Periodic housekeeping, called from the event loop.
+    ///
+    /// Reloads the menu when a watched config file has changed, reports
+    /// background actions that have finished, and keeps the list of running
+    /// ones up to date.
+    pub fn tick(&mut self) {
+        if self.watcher.poll() {
+            self.reload();
+        }
+        self.reap();
+        self.show_jobs();
+    }
+
+    /// Loads settings leniently, reporting whatever had to be skipped.
+    fn load(&self) -> Result<Settings, SettingsError> {
+        let path = self.config_path.as_ref().ok_or(SettingsError::NoHomeDir)?;
+        let settings = Settings::load_lenient(path);
+
+        for diagnostic in &settings.diagnostics {
+            match diagnostic.severity {
+                Severity::Warning => eprintln!("Warning: Skipped invalid config: {diagnostic}"),
+                Severity::Error => eprintln!("Error: {diagnostic}"),
+            }
+        }
+
+        Ok(settings)
     }
 
     fn build(&mut self) -> Menu {
-        let settings = match Settings::load() {
-            Ok(settings) => settings,
+        match self.load() {
+            Ok(settings) => {
+                check_terminal(&settings);
+                self.watch(&settings);
+                self.status.diagnostics.clone_from(&settings.diagnostics);
+                self.settings = Some(settings);
+            }
             Err(e) => {
                 eprintln!("Error loading settings: {e}");
-                // Return empty menu if settings fail to load
+                // Only the static items and the error remain
+                self.status.diagnostics = vec![Diagnostic::from_error(None, &e)];
                 self.settings = None;
-                return Menu::new();
             }
-        };
+        }
 
-        let menu = build_menu(&settings);
-        self.settings = Some(settings);
+        self.menu()
+    }
 
-        menu
+    /// Builds the menu for the current settings and status.
+    fn menu(&self) -> Menu {
+        match &self.settings {
+            Some(settings) => build_menu(settings, &self.status, &self.shown_jobs),
+            None => build_menu(&Settings::default(), &self.status, &self.shown_jobs),
+        }
     }
 
     pub fn handle_menu_event(&mut self, event: &MenuEvent) -> bool {
@@ -61,68 +202,440 @@ impl Application {
             return false;
         }
 
-        // O(1) lookup for dynamic menu items
-        let command = self.lookup_command(menu_id);
-        if let Some(cmd) = command {
-            let terminal = self
-                .settings
-                .as_ref()
-                .map(|s| Terminal::from(s.terminal.as_str()))
-                .unwrap_or_default();
-
-            if let Err(e) = terminal.launch(&cmd) {
+        if menu_id == MENU_ID_OPEN_ERROR {
+            self.open_error();
+            return false;
+        }
+
+        if menu_id == MENU_ID_COPY_ERROR {
+            if let Err(e) = clipboard::copy(&self.status.report()) {
                 eprintln!("Error: {e}");
             }
+            return false;
+        }
+
+        if let Some(id) = menu_id.strip_prefix(MENU_ID_STOP_PREFIX) {
+            self.stop(id);
+            return false;
+        }
+
+        if let Some(id) = menu_id.strip_prefix(MENU_ID_OUTPUT_PREFIX) {
+            self.show_output(id);
+            return false;
+        }
+
+        if let Some(path) = menu_id.strip_prefix(MENU_ID_GROUP_PREFIX) {
+            self.open_all(path);
+            return false;
+        }
+
+        // O(1) lookup for dynamic menu items
+        if let Some(mut launch) = self.lookup_command(menu_id) {
+            if !self.fill_in(&mut launch) || !launch.is_confirmed() {
+                return false;
+            }
+            if launch.background {
+                self.start(launch);
+                return false;
+            }
+            let result = launch.options.and_then(|options| {
+                self.terminal(&launch.terminal)
+                    .launch(&launch.command, &options)
+            });
+            self.record_launch(launch.name, result);
         }
 
         false
     }
 
     /// O(1) lookup for action and host commands by menu ID.
-    fn lookup_command(&self, menu_id: &str) -> Option<String> {
+    fn lookup_command(&self, menu_id: &str) -> Option<Launch> {
         let settings = self.settings.as_ref()?;
 
         // Check for action prefix: "action_{index}"
         if let Some(index_str) = menu_id.strip_prefix(MENU_ID_ACTION_PREFIX) {
             let index: usize = index_str.parse().ok()?;
             let action = settings.actions.get(NodeId::from_index(index))?;
-            return Some(action.cmd.clone());
+            return Some(action_launch(settings, action));
         }
 
         // Check for host prefix: "host_{index}"
         if let Some(index_str) = menu_id.strip_prefix(MENU_ID_HOST_PREFIX) {
             let index: usize = index_str.parse().ok()?;
             let host = settings.hosts.get(NodeId::from_index(index))?;
-            return Some(host.command());
+            return Some(Launch {
+                name: host.hostname.clone(),
+                command: match &settings.tmux.reattach {
+                    Some(session) => host.reattach_command(session),
+                    None => host.command(),
+                },
+                options: Ok(LaunchOptions {
+                    title: Some(host.hostname.clone()),
+                    keep_open: keep_open(settings.keep_open),
+                    shell: shell(settings),
+                    open_in: open_in(settings.open_in),
+                    ..LaunchOptions::default()
+                }),
+                terminal: Vec::new(),
+                background: false,
+                confirm: None,
+                template: None,
+            });
         }
 
         None
     }
 
-    fn configure(&self) {
-        let Some(path) = Settings::config_path() else {
+    /// Looks up a group by its path in an "Open all" menu ID, returning its
+    /// name and the launches of all the actions in it.
+    fn lookup_group(&self, path: &str) -> Option<(String, Vec<Launch>)> {
+        let settings = self.settings.as_ref()?;
+        let path = path
+            .split('.')
+            .map(str::parse)
+            .collect::<Result<Vec<usize>, _>>()
+            .ok()?;
+
+        let group = settings.actions.group(&path)?;
+        let Node::Group { name, .. } = group else {
+            return None;
+        };
+        let launches = settings
+            .actions
+            .leaves(group)
+            .map(|(_, action)| action_launch(settings, action))
+            .collect();
+        Some((name.clone(), launches))
+    }
+
+    /// Launches every action in a group at once, together in each terminal
+    /// the actions pick.
+    fn open_all(&mut self, path: &str) {
+        let Some((name, launches)) = self.lookup_group(path) else {
+            return;
+        };
+
+        let mut errors = Vec::new();
+        let mut commands = Vec::new();
+        for mut launch in launches {
+            if !self.fill_in(&mut launch) || !launch.is_confirmed() {
+                continue;
+            }
+            if launch.background {
+                self.start(launch);
+                continue;
+            }
+            match launch.options {
+                Ok(options) => commands.push((launch.terminal, (launch.command, options))),
+                Err(e) => errors.push(format!("{}: {e}", launch.name)),
+            }
+        }
+
+        let synchronize = self
+            .settings
+            .as_ref()
+            .and_then(|s| s.tmux.synchronize_panes)
+            .unwrap_or(true);
+        let (launchers, warnings) = self.terminals().choose_each(
+            commands
+                .iter()
+                .map(|(names, command)| (self.terminal_names(names), command.clone())),
+        );
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+        for (launcher, commands) in launchers {
+            if let Err(e) = launcher.launch_all(&name, &commands, synchronize) {
+                errors.push(e);
+            }
+        }
+
+        let result = if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        };
+        self.record_launch(name, result);
+    }
+
+    /// Asks for the values of the placeholders in a launch's command and
+    /// fills them in, starting from the values last used for the same
+    /// action. Returns `false` if the user cancels.
+    fn fill_in(&mut self, launch: &mut Launch) -> bool {
+        let Some(template) = launch.template.take() else {
+            return true;
+        };
+        let last = self.last_values.entry(launch.name.clone()).or_default();
+
+        let fields: Vec<_> = template
+            .placeholders()
+            .iter()
+            .map(|placeholder| dialog::Field {
+                label: placeholder.label(),
+                choices: placeholder.options(),
+                value: last.get(placeholder.label()).map(String::as_str),
+            })
+            .collect();
+        let Some(values) = dialog::prompt(&launch.name, &fields) else {
+            return false;
+        };
+
+        for (placeholder, value) in template.placeholders().iter().zip(values) {
+            last.insert(placeholder.label().to_string(), value);
+        }
+        launch.command = template.render(last);
+        true
+    }
+
+    /// Starts a command in the background, with its output going to a log
+    /// file named after it and when it started.
+    fn start(&mut self, launch: Launch) {
+        let result = launch.options.and_then(|options| {
+            let in_use: Vec<&Path> = self.jobs.iter().map(|running| running.job.log()).collect();
+            let log = log_file(&launch.name, &in_use)?;
+            Job::spawn(&launch.command, &options, log)
+        });
+        let result = result.map(|job| {
+            self.jobs.push(Running {
+                name: launch.name.clone(),
+                job,
+            });
+        });
+        self.record_launch(launch.name, result);
+    }
+
+    /// Finds a running job by the ID in a menu item.
+    fn job(&mut self, id: &str) -> Option<&mut Running> {
+        let id: u32 = id.parse().ok()?;
+        self.jobs.iter_mut().find(|running| running.job.id() == id)
+    }
+
+    /// Asks a job to stop. It's killed if it hasn't after [`STOP_TIMEOUT`].
+    fn stop(&mut self, id: &str) {
+        let Some(running) = self.job(id) else {
+            return;
+        };
+        if let Err(e) = running.job.stop() {
+            eprintln!("Error: Failed to stop '{}': {e}", running.name);
+        }
+        self.show_jobs();
+    }
+
+    /// Opens a job's log file with the default application.
+    fn show_output(&mut self, id: &str) {
+        let Some(running) = self.job(id) else {
+            return;
+        };
+        if let Err(e) = open::that(running.job.log()) {
+            eprintln!("Error opening output: {e}");
+        }
+    }
+
+    /// Updates the "Running" submenu and the tooltip if the jobs changed
+    /// since they were last shown.
+    fn show_jobs(&mut self) {
+        let jobs: Vec<_> = self
+            .jobs
+            .iter()
+            .map(|running| RunningJob {
+                id: running.job.id(),
+                name: running.name.clone(),
+                minutes: running.job.elapsed().as_secs() / 60,
+                stopping: running.job.is_stopping(),
+            })
+            .collect();
+        if jobs == self.shown_jobs {
+            return;
+        }
+
+        self.tray.set_running(jobs.len());
+        self.shown_jobs = jobs;
+        self.tray.set_menu(self.menu());
+    }
+
+    /// Forgets background actions that have finished, with a notification
+    /// about how each one went, and kills those that ignore being stopped.
+    ///
+    /// A stopped job is kept until everything it started is gone, so
+    /// processes that ignore `SIGTERM` are killed even if the command isn't.
+    /// If some are still there [`KILL_TIMEOUT`] after that, the job is given
+    /// up on.
+    fn reap(&mut self) {
+        let mut notifications = Vec::new();
+        self.jobs.retain_mut(|running| {
+            let status = match running.job.try_wait() {
+                Ok(Some(status))
+                    if !running.job.is_stopping()
+                        || !running.job.poll_group_running(GROUP_POLL_INTERVAL) =>
+                {
+                    status
+                }
+                Ok(_) => {
+                    if let Err(e) = running.job.kill_if_overdue(STOP_TIMEOUT) {
+                        eprintln!("Error: Failed to kill '{}': {e}", running.name);
+                    }
+                    return true;
+                }
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    return false;
+                }
+            };
+
+            let output = format!("Output is in {}", running.job.log().display());
+            notifications.push(if running.job.is_stopping() {
+                Notification::new(format!("'{}' stopped", running.name), output)
+            } else if status.success() {
+                Notification::new(format!("'{}' finished", running.name), output)
+            } else {
+                eprintln!("Error: '{}' {}", running.name, describe_exit(status));
+                Notification::error(
+                    format!("'{}' failed", running.name),
+                    format!("It {}. {output}", describe_exit(status)),
+                )
+            });
+            false
+        });
+
+        let overdue = |running: &mut Running| {
+            running
+                .job
+                .killed_for()
+                .is_some_and(|killed| killed >= KILL_TIMEOUT)
+        };
+        for running in self.jobs.extract_if(.., overdue) {
+            eprintln!(
+                "Error: '{}' couldn't be stopped, process group {} is left",
+                running.name,
+                running.job.id()
+            );
+            notifications.push(Notification::error(
+                format!("'{}' couldn't be stopped", running.name),
+                format!(
+                    "Processes in group {} survived SIGKILL. Output is in {}",
+                    running.job.id(),
+                    running.job.log().display()
+                ),
+            ));
+            running.job.detach();
+        }
+
+        for notification in &notifications {
+            self.notify(notification);
+        }
+    }
+
+    /// Picks the first installed terminal from `names`, or from the global
+    /// setting if `names` is empty, warning about every one that was skipped.
+    fn terminal(&self, names: &[String]) -> Launcher {
+        let (launcher, warnings) = self.terminals().choose(self.terminal_names(names));
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+        launcher
+    }
+
+    /// Returns the terminals to try for an action: `names`, or the global
+    /// setting if `names` is empty.
+    fn terminal_names<'a>(&'a self, names: &'a [String]) -> &'a [String] {
+        match (names, &self.settings) {
+            ([], Some(settings)) => settings.terminal.as_slice(),
+            _ => names,
+        }
+    }
+
+    /// Returns the built-in terminals along with those defined in the
+    /// settings.
+    fn terminals(&self) -> Terminals {
+        self.settings.as_ref().map(terminals).unwrap_or_default()
+    }
+
+    /// Shows a failed launch in the menu until the next successful one.
+    fn record_launch(&mut self, name: String, result: Result<(), String>) {
+        let failure = result.err().map(|message| {
+            eprintln!("Error: {message}");
+            LaunchFailure { name, message }
+        });
+
+        if let Some(failure) = &failure {
+            self.notify(&Notification::error(
+                format!("Failed to launch '{}'", failure.name),
+                &failure.message,
+            ));
+        }
+
+        if failure.is_some() || self.status.launch_failure.is_some() {
+            self.status.launch_failure = failure;
+            self.tray.set_menu(self.menu());
+        }
+    }
+
+    /// Shows a desktop notification, unless disabled in the settings.
+    fn notify(&self, notification: &Notification) {
+        if self.settings.as_ref().is_some_and(|s| !s.notifications) {
+            return;
+        }
+
+        if let Err(e) = self.notifier.notify(notification) {
+            eprintln!("Warning: {e}");
+        }
+    }
+
+    fn configure(&mut self) {
+        let Some(path) = self.config_path.clone() else {
             eprintln!("Error: Could not determine config path");
             return;
         };
 
+        self.edit(&path, None);
+    }
+
+    /// Opens the file of the most relevant config problem at its location.
+    fn open_error(&mut self) {
+        let Some(Diagnostic {
+            file: Some(file),
+            location,
+            ..
+        }) = self.status.first_located().cloned()
+        else {
+            return;
+        };
+
+        self.edit(&file, location);
+    }
+
+    /// Opens a file in the configured editor, at `location` if the editor
+    /// supports jumping to a position.
+    fn edit(&mut self, path: &Path, location: Option<Location>) {
         let editor = self
             .settings
             .as_ref()
-            .map_or("default", |s| s.editor.as_str());
-
-        let path_display = path.display();
-        let result = match editor {
-            "default" => open::that(&path),
-            editor if is_terminal_editor(editor) => {
-                let cmd = format!("{editor} {path_display}");
-                let terminal = self
-                    .settings
-                    .as_ref()
-                    .map(|s| Terminal::from(s.terminal.as_str()))
-                    .unwrap_or_default();
-                terminal.launch(&cmd).map_err(std::io::Error::other)
-            }
-            editor => open::with(&path, editor),
+            .map_or("default", |s| s.editor.as_str())
+            .to_string();
+
+        let goto = location.and_then(|location| goto_args(&editor, path, location));
+
+        if is_terminal_editor(&editor) {
+            let args = goto.unwrap_or_else(|| vec![path.display().to_string()]);
+            let command = shell_words::join(std::iter::once(&editor).chain(&args));
+            // The editor's terminal has done its job once the editor quits
+            let options = LaunchOptions {
+                keep_open: KeepOpen::Close,
+                shell: self.settings.as_ref().map(shell).unwrap_or_default(),
+                ..LaunchOptions::default()
+            };
+            let result = self.terminal(&[]).launch(&command, &options);
+            self.record_launch(editor, result);
+            return;
+        }
+
+        let result = match (editor.as_str(), goto) {
+            ("default", _) => open::that(path),
+            (editor, Some(args)) => Command::new(editor).args(args).spawn().map(|mut child| {
+                // Reaped once the editor quits, so it doesn't linger as a zombie
+                std::thread::spawn(move || child.wait());
+            }),
+            (editor, None) => open::with(path, editor),
         };
 
         if let Err(e) = result {
@@ -130,12 +643,302 @@ impl Application {
         }
     }
 
+    /// Watches the files the given settings were loaded from.
+    fn watch(&mut self, settings: &Settings) {
+        if self.watcher.paths() != settings.sources {
+            self.watcher = Watcher::new(settings.sources.clone());
+        }
+    }
+
+    /// Rebuilds the menu from freshly loaded settings.
+    ///
+    /// Invalid entries are skipped, but if a file that loaded before can no
+    /// longer be used at all (e.g. it's saved mid-edit with a syntax error),
+    /// the previous settings and menu are kept.
     fn reload(&mut self) {
-        let menu = self.build();
-        self.tray.set_menu(menu);
+        let settings = match self.load() {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Error reloading settings, keeping previous menu: {e}");
+                return;
+            }
+        };
+
+        // Show the new problems either way
+        let previous_diagnostics =
+            std::mem::replace(&mut self.status.diagnostics, settings.diagnostics.clone());
+        self.watch(&settings);
+
+        let kept = self
+            .settings
+            .as_ref()
+            .is_some_and(|previous| newly_broken(previous, &settings));
+        if kept {
+            eprintln!("Error reloading settings, keeping previous menu");
+        } else {
+            check_terminal(&settings);
+            self.settings = Some(settings);
+        }
+
+        self.tray.set_menu(self.menu());
+
+        // Only notify when the outcome changed, not on every save
+        let diagnostics = &self.status.diagnostics;
+        if *diagnostics == previous_diagnostics {
+            return;
+        }
+        let notification = if kept {
+            Notification::error(
+                "Config error, keeping previous menu",
+                summarize(diagnostics),
+            )
+        } else if diagnostics.is_empty() {
+            Notification::new("Config reloaded", "All config problems are fixed.")
+        } else {
+            Notification::error("Config reloaded with problems", summarize(diagnostics))
+        };
+        self.notify(&notification);
+    }
+}
+
+/// Lists the first few problems for a notification body.
+fn summarize(diagnostics: &[Diagnostic]) -> String {
+    let mut lines: Vec<_> = diagnostics
+        .iter()
+        .take(MAX_NOTIFIED_PROBLEMS)
+        .map(ToString::to_string)
+        .collect();
+    if diagnostics.len() > MAX_NOTIFIED_PROBLEMS {
+        lines.push(format!(
+            "… and {} more",
+            diagnostics.len() - MAX_NOTIFIED_PROBLEMS
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Returns `true` if a file had to be skipped entirely in `current` that was
+/// usable in `previous`.
+fn newly_broken(previous: &Settings, current: &Settings) -> bool {
+    let broken = |settings: &Settings| {
+        settings
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| d.file.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let before = broken(previous);
+    broken(current).iter().any(|file| !before.contains(file))
+}
+
+/// Returns the launch of an action, with the global settings for whatever
+/// the action doesn't set.
+fn action_launch(settings: &Settings, action: &Action) -> Launch {
+    Launch {
+        name: action.name.clone(),
+        command: action.cmd.clone(),
+        options: action
+            .environment()
+            .map(|env| LaunchOptions {
+                cwd: action.working_dir(),
+                env,
+                title: Some(action.name.clone()),
+                keep_open: keep_open(action.keep_open.unwrap_or(settings.keep_open)),
+                shell: shell(settings),
+                open_in: open_in(action.open_in.unwrap_or(settings.open_in)),
+            })
+            .map_err(|e| e.to_string()),
+        terminal: action.terminal.clone(),
+        background: action.mode == Some(Mode::Background),
+        confirm: action
+            .confirm
+            .as_ref()
+            .and_then(|confirm| confirm.message(&action.name)),
+        // Loading the config skips actions with malformed placeholders
+        template: action
+            .template()
+            .ok()
+            .filter(|template| !template.is_literal()),
+    }
+}
+
+/// Returns a new log file for the output of a background action, creating
+/// the directory it's in and removing the action's oldest logs other than
+/// those `in_use`.
+fn log_file(name: &str, in_use: &[&Path]) -> Result<PathBuf, String> {
+    let dir = Settings::log_dir().unwrap_or_else(|| std::env::temp_dir().join("xshuttle"));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create log directory {}: {e}", dir.display()))?;
+
+    let file: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let prefix = format!("{file}-");
+    prune_logs(&dir, &prefix, in_use);
+
+    // Named by start time, so runs of the same action don't share a file
+    let mut started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    loop {
+        let path = dir.join(format!("{prefix}{started}.log"));
+        if !path.exists() {
+            return Ok(path);
+        }
+        started += 1;
+    }
+}
+
+/// Removes the oldest logs in `dir` named `{prefix}{start time}.log`, to
+/// make room for a new one within [`KEPT_LOGS`].
+fn prune_logs(dir: &Path, prefix: &str, in_use: &[&Path]) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut logs: Vec<(u128, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let started = name
+                .to_str()?
+                .strip_prefix(prefix)?
+                .strip_suffix(".log")?
+                .parse()
+                .ok()?;
+            Some((started, entry.path()))
+        })
+        .filter(|(_, path)| !in_use.contains(&path.as_path()))
+        .collect();
+    logs.sort_unstable();
+
+    let excess = (logs.len() + 1).saturating_sub(KEPT_LOGS);
+    for (_, path) in logs.drain(..excess) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            eprintln!("Warning: Failed to remove old log {}: {e}", path.display());
+        }
+    }
+}
+
+/// Describes how a process ended, e.g. "exited with code 1".
+fn describe_exit(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with code {code}"),
+        (None, Some(signal)) => format!("was killed by signal {signal}"),
+        (None, None) => "ended".to_string(),
+    }
+}
+
+/// Returns the built-in terminals along with those defined in `settings`,
+/// and tmux with its configured session.
+fn terminals(settings: &Settings) -> Terminals {
+    let tmux = &settings.tmux;
+    Terminals::new(
+        settings
+            .terminals
+            .iter()
+            .map(|(name, terminal)| Custom {
+                name: name.clone(),
+                bin: terminal.program(),
+                args: terminal.args.clone(),
+            })
+            .collect(),
+    )
+    .with_tmux(
+        tmux.session
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SESSION.to_string()),
+        tmux.socket.clone(),
+    )
+}
+
+/// Returns the shell to run commands in, with the platform's defaults for
+/// whatever isn't set.
+fn shell(settings: &Settings) -> Shell {
+    let default = Shell::default();
+    Shell {
+        program: settings.shell_program().or(default.program),
+        login: settings.login_shell.unwrap_or(default.login),
+    }
+}
+
+/// Converts the `keep_open` setting for the terminal crate.
+fn keep_open(keep_open: settings::KeepOpen) -> KeepOpen {
+    match keep_open {
+        settings::KeepOpen::Shell => KeepOpen::Shell,
+        settings::KeepOpen::Close => KeepOpen::Close,
+        settings::KeepOpen::CloseOnSuccess => KeepOpen::CloseOnSuccess,
+        settings::KeepOpen::Pause => KeepOpen::Pause,
     }
 }
 
+/// Converts the `open_in` setting for the terminal crate.
+fn open_in(open_in: settings::OpenIn) -> OpenIn {
+    match open_in {
+        settings::OpenIn::Window => OpenIn::Window,
+        settings::OpenIn::Tab => OpenIn::Tab,
+    }
+}
+
+/// Warns about configured terminals that aren't known, globally or on an
+/// action, suggesting the closest known name, and about defined terminals
+/// that can't run a command.
+fn check_terminal(settings: &Settings) {
+    for (name, terminal) in &settings.terminals {
+        if !terminal.args.iter().any(|arg| arg.contains("{cmd}")) {
+            eprintln!("Warning: Terminal '{name}' has no {{cmd}} in its args");
+        }
+    }
+
+    let terminals = terminals(settings);
+    let names = settings
+        .actions
+        .iter()
+        .flat_map(|(_, action)| &action.terminal)
+        .chain(&settings.terminal);
+
+    for name in names {
+        if terminals.is_known(name) {
+            continue;
+        }
+
+        match did_you_mean(name, terminals.names()) {
+            Some(suggestion) => {
+                eprintln!("Warning: Unknown terminal '{name}', did you mean '{suggestion}'?");
+            }
+            None => eprintln!("Warning: Unknown terminal '{name}', it will be skipped"),
+        }
+    }
+}
+
+/// Returns the arguments that open `path` at `location` in `editor`, for
+/// editors known to support it.
+fn goto_args(editor: &str, path: &Path, location: Location) -> Option<Vec<String>> {
+    let path = path.display().to_string();
+    let Location { line, column } = location;
+    let name = Path::new(editor).file_name()?.to_str()?;
+
+    let args = match name {
+        "vim" | "vi" | "nvim" | "ne" | "joe" | "pico" => vec![format!("+{line}"), path],
+        "nano" => vec![format!("+{line},{column}"), path],
+        "emacs" | "micro" => vec![format!("+{line}:{column}"), path],
+        "code" | "codium" | "code-insiders" => {
+            vec!["--goto".to_string(), format!("{path}:{line}:{column}")]
+        }
+        "subl" | "zed" | "hx" | "helix" => vec![format!("{path}:{line}:{column}")],
+        _ => return None,
+    };
+    Some(args)
+}
+
 fn is_terminal_editor(editor: &str) -> bool {
     matches!(
         editor,